@@ -3,8 +3,12 @@ use std::{io::Write, str::FromStr};
 
 pub fn take_input<T: FromStr>(prompt: &str) -> Result<T, <T as FromStr>::Err> {
     let mut input_string = String::new();
-    print!("\n{} >> ", prompt);
-    std::io::stdout().flush();
+    {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        write!(stdout, "\n{} >> ", prompt).expect("Failed to write prompt");
+        stdout.flush().expect("Failed to flush stdout");
+    }
     std::io::stdin()
         .read_line(&mut input_string)
         .ok()
@@ -0,0 +1,50 @@
+//! Ctrl-C during a long scan or pointer map drops back to the prompt instead of killing
+//! the program (and any frozen values with it), a second Ctrl-C quits for real
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use nix::{
+    libc,
+    sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal},
+};
+
+use crate::error::{BetrayalError, BetrayalResult};
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    if CANCELLED.swap(true, Ordering::SeqCst) {
+        // nothing picked the first one up, the user really wants out
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// replaces the default SIGINT behaviour, call once at startup
+pub fn install() -> BetrayalResult<()> {
+    let action = SigAction::new(
+        SigHandler::Handler(on_interrupt),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    unsafe { sigaction(Signal::SIGINT, &action) }
+        .map(drop)
+        .map_err(|e| BetrayalError::Other(anyhow::anyhow!("installing a Ctrl-C handler :: {}", e)))
+}
+
+pub fn requested() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// forgets a Ctrl-C that was already handled (or pressed while idle)
+pub fn reset() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+/// bails out of long operations once Ctrl-C was pressed
+pub fn check() -> BetrayalResult<()> {
+    if requested() {
+        Err(BetrayalError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
@@ -1,52 +1,166 @@
-use crate::memory::ReadFromBytes;
-use crate::{error::BetrayalResult, Filter};
+use crate::memory::{BytePattern, FromUserInput, NumberKind, ReadFromBytes};
+use crate::neighbour_values::NeighbourValuesQuery;
+use crate::reclass::scripting::ScriptFilter;
+use crate::{error::BetrayalResult, Filter, PointerMapLimits, SortOrder, VARIABLE_TYPES};
 use crate::{BetrayalError, Writer};
+use std::path::PathBuf;
 use std::str::FromStr;
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Command<T: ReadFromBytes> {
     PerformFilter(Filter<T>),
+    PerformUnknownScan,
+    /// snapshot of / changes within maps whose path contains this, see `perform_changed_in`
+    ChangedIn(String),
+    /// index of the result whose value is searched for everywhere
+    FindCopies(usize),
     KeepWriting(Writer<T>),
+    ListFrozen,
+    Unfreeze(usize),
     Write(Writer<T>),
+    WriteAddress(usize, T),
+    /// raw bytes written straight to the address, see "wbytes" and "wstr"
+    WriteBytes(usize, Vec<u8>),
+    WriteAll(T),
+    KeepWritingAll(T),
     Quit,
     Refresh,
+    /// refresh that also drops the addresses that can't be read anymore
+    RefreshPrune,
+    /// drops unaligned results that duplicate an aligned neighbour
+    Dedupe,
+    Undo,
+    Clear,
+    /// keeps the result addresses but reads them as another type, eg. "u32"
+    Reinterpret(String),
+    ToggleHex,
     Help,
     AddAddress(usize),
     AddAddressRange(usize, usize),
-    PointerMapU32(u32, u32),
-    PointerMapU64(u64, u64),
+    /// replaces all the results with just this address
+    Only(usize),
+    DeleteAddress(usize),
+    Label(usize, String),
+    /// reads the results at both indices and tells how they differ
+    Compare(usize, usize),
+    /// address, window and levels, see `pointer_map`
+    PointerMapU32(u32, u32, usize),
+    PointerMapU64(u64, u64, usize),
+    ExportPointerMap(PathBuf),
+    StaticPointerChains,
+    PointerChains(usize),
+    FindNeighbourValues(NeighbourValuesQuery<T>),
+    Page(usize),
+    PageSize(usize),
+    Sort(SortOrder),
+    ExportCsv(PathBuf),
+    /// two "export csv" files, older one first
+    Diff(PathBuf, PathBuf),
+    Watch(u64),
+    /// base address script (see reclass) and pointer offsets
+    Resolve(String, Vec<usize>),
+    /// target address and how far before it the pointers can point
+    NearPointers(usize, usize),
+    /// start address and length in bytes
+    Dump(usize, usize),
+    /// address to read as every type
+    Guess(usize),
+    /// index of the result to put a hardware watchpoint on
+    #[cfg(target_arch = "x86_64")]
+    WatchWrites(usize),
+    /// lists memory regions, optionally only those whose path contains this
+    Maps(Option<String>),
 }
 
 
 macro_rules! parse_or_bad_command {
     ($value:expr) => {
-        $value
-            .parse()
-            .map_err(|_e| BetrayalError::BadCommand(format!("invalid value")))?
+        FromUserInput::from_user_input($value)
+            .ok_or_else(|| BetrayalError::BadCommand(format!("invalid value")))?
     };
 }
 
+/// a bigger "p m" window makes nearly every value in memory look like a pointer
+pub const MAX_POINTER_WINDOW: u64 = 64 * 1024;
+
+/// "dump" is read in one go and printed to the terminal, so it's kept to what's readable there
+pub const MAX_DUMP_LEN: usize = 64 * 1024;
+
 pub const HELP_TEXT: &str = r#"
 [ :: Betrayal Engine :: ]
 author: wojciech.brozek@niedzwiedz.it
 github: https://github.com/Niedzwiedzw/betratal_engine
 
 COMMANDS:
-""                               -> refreshes current results
+(addresses and values can also be written in hex or binary, eg. 0x1F4 or 0b1010, that's the raw bit pattern of the type so 0xFFFFFFFF is -1 for i32)
+""                               -> refreshes current results (addresses that can't be read anymore keep their last value, marked as unreadable)
+"refresh-prune"                  -> refreshes current results and drops the addresses that can't be read anymore
 "a <address> <address?>          -> adds address to the list (or range of addresses if second argument is present)
+"only <address>"                 -> replaces the results with just <address> (eg. the end of a pointer chain), "u" brings the old ones back
+"dump <address> <len>"           -> prints a hexdump of <len> bytes starting at <address> (doesn't touch the results, 64 KiB at most)
+"guess <address>"                -> reads 8 bytes at <address> as every type and marks the plausible ones
+"maps <filter?>"                 -> lists memory regions of the process (only those whose path contains <filter>), handy for picking --region
+"d <index>"                      -> removes the result at <index> from the list
+"cmp <index_a> <index_b>"        -> reads both results and tells whether they are equal and by how much they differ (eg. to verify a mirror found with "f eq @3")
+"name <index> <label>"           -> gives the result at <index> a human readable name
+"watch <ms>"                     -> refreshes and prints the results every <ms> milliseconds until enter is pressed
+"page <n>"                       -> displays the <n>th page of results (when there are too many to fit on one)
+"page size <n>"                  -> displays <n> results per page
+"sort <addr/value/value-desc>"   -> orders the displayed results (indices follow that order)
+"hex"                            -> toggles displaying values as hex (integers only)
+"export csv <path>"              -> writes the results to a .csv file (for spreadsheets)
+"diff <before.csv> <after.csv>"  -> compares two "export csv" files, lists changed values (biggest change first), added and removed addresses
 "q"                              -> quits the program
+"clear"                          -> drops all the results, the next filter starts a fresh scan
+"dedupe"                         -> drops unaligned results (from a byte-granular scan) when an overlapping aligned result holds the same value
+"reinterpret <type>"             -> keeps the addresses of the results but reads them as <type> from now on (i8 u8 i16 u16 i32 u32 i64 u64 f32 f64), frozen values and names are dropped
+"u"                              -> undoes the last scan, restoring previous results
 "h" or "?" or "help"             -> prints this help message
 "w <index> <value>"              -> writes a specified value to address at results
+"wa <address> <value>"           -> writes a value straight to <address>, it doesn't have to be in the results
+"wbytes <address> 48 65 6C"      -> writes raw bytes (hex) to <address>, eg. to patch a few instructions
+"wstr <address> <text>"          -> writes <text> followed by a 0 byte to <address>, eg. to rename something
 "n <window_size> 1 2 14"         -> lists all possible windows of <window_size> that contain 1 2 and 14 in no specific order, useful for finding structs
+"na <window_size> 0.1 1.5 20"    -> same as "n" but the values only have to be within 0.1 of 1.5 and 20 (f32 / f64 only)
+"wall <value>"                   -> writes <value> to every address in the results
+"kall <value>"                   -> freezes every address in the results at <value>
 "k <index> <value>"              -> same as "w" but does that in a loop so that value stays the same (god mode etc)
+"freeze <index> <value>"         -> same as "k"
+"frozen"                         -> lists all frozen addresses
+"unfreeze <index>"               -> stops writing to the address at <index> of the "frozen" list
 "f u"                            -> a NO-OP filter, for new scans it will match all the values (very memory intensive), equivalent to refresh for subsequent scans
+"f unknown"                      -> snapshots every value in writable memory so that you can narrow it down with "f inc", "f dec" etc. (unknown initial value)
 "f e 2137"                       -> finds values equal to 2137
+"f eq @3"                        -> finds every address currently holding the same value as the result at index 3 (a fresh scan)
+"f ae 1.5 0.001"                 -> finds values equal to 1.5 with a tolerance of 0.001 (for integers it's a +/- window)
 "f c 15"                         -> finds values that changed by 15 compared to previous scan (does nothing for initial scan)
+"f cp 10 <max?>"                 -> finds values that changed by at least 10% (and at most <max>%) compared to previous scan (does nothing for initial scan)
+"f inc"                          -> finds values that increased compared to previous scan (does nothing for initial scan)
+"f dec"                          -> finds values that decreased compared to previous scan (does nothing for initial scan)
+"f unch"                         -> finds values that did not change compared to previous scan (does nothing for initial scan)
+"f changed"                      -> finds values that changed by any amount compared to previous scan (does nothing for initial scan)
+"f changed-in <region>"          -> the first time snapshots only the maps whose path contains <region> (eg. a library), then keeps the values in them that changed since, "clear" to start over
+"f nan"                          -> finds values that are NaN (f32 / f64 only)
+"f fin"                          -> finds values that are neither NaN nor infinite (f32 / f64 only)
 "f r 15 300"                     -> finds values between 15 and 300
-"p m <u32/u64> <address> <depth> -> displays a pointer map for a given address (either 32 or 64 bit wide), depth affects performance
+"f gt 100"                       -> finds values greater than 100
+"f lt 50"                        -> finds values less than 50
+"f mod 10"                       -> finds values that are a multiple of 10 (integers only)
+"f script <expr>"                -> finds values for which a rhai expression is true, eg. value > 100 && address % 8 == 0 (integers only)
+"f bits 0xF0 0x50"               -> finds values where the bits of the mask (0xF0) are equal to 0x50 (integers only)
+"f3 1.5 20 -3 0.1"               -> finds 3 consecutive values close to 1.5, 20 and -3 (eg. x y z position) with a tolerance of 0.1, the address is the one of the first value
+"aob 48 8B ?? ?? 89"             -> finds every address where the bytes match the pattern (hex bytes, ?? is a wildcard)
+"ptr <address> <slack>"          -> lists addresses holding a pointer to at most <slack> bytes before <address> (with the offset), the first step of manual pointer scanning
+"p m <u32/u64> <addr> <w> <l?>"  -> displays a pointer map for <addr> (32 or 64 bit pointers), a value counts as a pointer when it's at most <w> bytes before the address (the window, eg. 0x100 to catch pointers to the struct a field is in), chains go at most <l> pointers deep (levels, default 5), bigger values of both are much slower (the window can be 64 KiB at most)
+"p chains <address>"             -> lists every chain of the last pointer map that ends at <address>
+"p static"                       -> lists pointer chains of the last pointer map that start at a static address
+"resolve <base> <off1> <off2>"   -> follows a pointer chain, <base> is a reclass script without spaces, eg. static_address(PID,"game")+0x10
+"p export <path>"                -> writes the last pointer map to a graphviz .dot file
 
 FIND OUT WHAT WRITES TO THIS ADDRESS:
-not implemented, use gdb (gnu debugger)
+"ww <index>"                     -> puts a hardware watchpoint on the result at <index> and lists the instructions writing to it until enter is pressed (x86-64 only)
+this uses ptrace, so run as root or allow it first: echo 0 | sudo tee /proc/sys/kernel/yama/ptrace_scope
+for anything more involved use gdb (gnu debugger)
 sudo gdb --pid <process-id>  # atteches to the process
 watch *<value_address>       # (sets a breakpoint)
 c                            # (continue)
@@ -61,37 +175,297 @@ fn command_parser<T: ReadFromBytes>(i: &str) -> BetrayalResult<Command<T>> {
     let command = i.split_whitespace().collect::<Vec<_>>();
     match &command[..] {
         [] => Ok(Command::Refresh),
+        ["refresh-prune"] => Ok(Command::RefreshPrune),
+        ["dedupe"] => Ok(Command::Dedupe),
         ["h" | "?" | "help"] => Ok(Command::Help),
         ["q"] => Ok(Command::Quit),
+        ["u"] => Ok(Command::Undo),
+        ["clear"] => Ok(Command::Clear),
+        ["reinterpret", variable_type] if VARIABLE_TYPES.contains(variable_type) => {
+            Ok(Command::Reinterpret(variable_type.to_string()))
+        }
+        ["reinterpret", variable_type] => Err(BetrayalError::BadCommand(format!(
+            "unsupported type {}, try one of {}",
+            variable_type,
+            VARIABLE_TYPES.join(" ")
+        ))),
+        ["hex"] => Ok(Command::ToggleHex),
+        ["page", "size", page_size] => Ok(Command::PageSize(parse_or_bad_command!(page_size))),
+        ["page", page] => Ok(Command::Page(parse_or_bad_command!(page))),
+        ["sort", order] => Ok(Command::Sort(order.parse()?)),
+        ["export", "csv", path] => Ok(Command::ExportCsv(PathBuf::from(path))),
+        ["diff", before, after] => Ok(Command::Diff(PathBuf::from(before), PathBuf::from(after))),
+        ["watch", interval] => Ok(Command::Watch(parse_or_bad_command!(interval))),
+        ["frozen"] => Ok(Command::ListFrozen),
+        ["unfreeze", index] => Ok(Command::Unfreeze(parse_or_bad_command!(index))),
         ["w", index, value] => Ok(Command::Write((
             parse_or_bad_command!(index),
             parse_or_bad_command!(value),
         ))),
+        ["wall", value] => Ok(Command::WriteAll(parse_or_bad_command!(value))),
+        ["kall", value] => Ok(Command::KeepWritingAll(parse_or_bad_command!(value))),
+        ["wa", address, value] => Ok(Command::WriteAddress(
+            parse_or_bad_command!(address),
+            parse_or_bad_command!(value),
+        )),
+        ["wbytes", address, bytes @ ..] if !bytes.is_empty() => Ok(Command::WriteBytes(
+            parse_or_bad_command!(address),
+            parse_bytes(bytes)?,
+        )),
+        ["wstr", address, _, ..] => {
+            let mut bytes = raw_remainder(i, 2).as_bytes().to_vec();
+            bytes.push(0);
+            Ok(Command::WriteBytes(parse_or_bad_command!(address), bytes))
+        }
         ["a", address] => Ok(Command::AddAddress(parse_or_bad_command!(address))),
+        ["only", address] => Ok(Command::Only(parse_or_bad_command!(address))),
         ["a", address_start, address_end] => Ok(Command::AddAddressRange(
             parse_or_bad_command!(address_start),
             parse_or_bad_command!(address_end),
         )),
-        ["k", index, value] => Ok(Command::KeepWriting((
+        #[cfg(target_arch = "x86_64")]
+        ["ww", index] => Ok(Command::WatchWrites(parse_or_bad_command!(index))),
+        #[cfg(not(target_arch = "x86_64"))]
+        ["ww", _index] => Err(BetrayalError::BadCommand(
+            "hardware watchpoints are only supported on x86-64".to_string(),
+        )),
+        ["ptr", target, slack] => Ok(Command::NearPointers(
+            parse_or_bad_command!(target),
+            parse_or_bad_command!(slack),
+        )),
+        ["maps"] => Ok(Command::Maps(None)),
+        ["maps", filter] => Ok(Command::Maps(Some(filter.to_string()))),
+        ["guess", address] => Ok(Command::Guess(parse_or_bad_command!(address))),
+        ["dump", address, len] => {
+            let len: usize = parse_or_bad_command!(len);
+            if len > MAX_DUMP_LEN {
+                return Err(BetrayalError::BadCommand(format!(
+                    "can't dump more than {} bytes at once",
+                    MAX_DUMP_LEN
+                )));
+            }
+            Ok(Command::Dump(parse_or_bad_command!(address), len))
+        }
+        ["d", index] => Ok(Command::DeleteAddress(parse_or_bad_command!(index))),
+        ["cmp", a, b] => Ok(Command::Compare(
+            parse_or_bad_command!(a),
+            parse_or_bad_command!(b),
+        )),
+        ["name", index, label @ ..] if !label.is_empty() => Ok(Command::Label(
+            parse_or_bad_command!(index),
+            label.join(" "),
+        )),
+        ["k" | "freeze", index, value] => Ok(Command::KeepWriting((
             parse_or_bad_command!(index),
             parse_or_bad_command!(value),
         ))),
         ["f", "u"] => Ok(Command::PerformFilter(Filter::Any)),
+        ["f", "unknown"] => Ok(Command::PerformUnknownScan),
+        ["f", "inc"] => Ok(Command::PerformFilter(Filter::Increased)),
+        ["f", "dec"] => Ok(Command::PerformFilter(Filter::Decreased)),
+        ["f", "unch"] => Ok(Command::PerformFilter(Filter::Unchanged)),
+        ["f", "changed"] => Ok(Command::PerformFilter(Filter::Changed)),
+        ["f", "changed-in", region @ ..] if !region.is_empty() => {
+            Ok(Command::ChangedIn(region.join(" ")))
+        }
+        ["f", "script", _expression @ ..] if T::IS_FLOAT => Err(BetrayalError::BadCommand(
+            "script filters only work in integer modes".to_string(),
+        )),
+        ["f", "script", expression @ ..] if !expression.is_empty() => Ok(Command::PerformFilter(
+            Filter::Script(ScriptFilter::new(&expression.join(" "))?),
+        )),
+        ["f", "nan" | "fin"] if !T::IS_FLOAT => Err(BetrayalError::BadCommand(
+            "nan / fin filters only work in f32 and f64 mode".to_string(),
+        )),
+        ["f", "nan"] => Ok(Command::PerformFilter(Filter::IsNan)),
+        ["f", "fin"] => Ok(Command::PerformFilter(Filter::IsFinite)),
+        ["f", "eq", index] => match index.strip_prefix('@') {
+            Some(index) => Ok(Command::FindCopies(parse_or_bad_command!(index))),
+            None => Err(BetrayalError::BadCommand(format!(
+                "expected a result index like @3, got {}",
+                index
+            ))),
+        },
+        ["f", "mod", _divisor] if T::IS_FLOAT => Err(BetrayalError::BadCommand(
+            "mod filter only works in integer modes".to_string(),
+        )),
+        ["f", "mod", divisor] => {
+            let divisor: T = parse_or_bad_command!(divisor);
+            if divisor == T::default() {
+                return Err(BetrayalError::BadCommand(
+                    "can't filter by divisibility by 0".to_string(),
+                ));
+            }
+            Ok(Command::PerformFilter(Filter::DivisibleBy(divisor)))
+        }
         ["f", compare, value] => Ok(Command::PerformFilter(match *compare {
             "e" => Filter::IsEqual(parse_or_bad_command!(value)),
             "c" => Filter::ChangedBy(parse_or_bad_command!(value)),
+            "gt" => Filter::GreaterThan(parse_or_bad_command!(value)),
+            "lt" => Filter::LessThan(parse_or_bad_command!(value)),
             _ => return Err(BetrayalError::BadCommand("command not found".to_string())),
         })),
-        ["f", "r", start, end] => Ok(Command::PerformFilter(Filter::InRange((
-            parse_or_bad_command!(start),
-            parse_or_bad_command!(end),
-        )))),
-        ["p", "m", "u32", address, depth] => Ok(Command::PointerMapU32(parse_or_bad_command!(address), parse_or_bad_command!(depth))),
-        ["p", "m", "u64", address, depth] => Ok(Command::PointerMapU64(parse_or_bad_command!(address), parse_or_bad_command!(depth))),
+        ["f", "bits", _mask, _expected] if T::IS_FLOAT => Err(BetrayalError::BadCommand(
+            "bits filter only works in integer modes".to_string(),
+        )),
+        ["f", "bits", mask, expected] => Ok(Command::PerformFilter(Filter::BitsSet(
+            parse_or_bad_command!(mask),
+            parse_or_bad_command!(expected),
+        ))),
+        ["f", "ae", value, epsilon] => Ok(Command::PerformFilter(Filter::IsApproximately(
+            parse_or_bad_command!(value),
+            parse_or_bad_command!(epsilon),
+        ))),
+        ["f", "cp", min] => Ok(Command::PerformFilter(Filter::ChangedByPercent(
+            parse_or_bad_command!(min),
+            None,
+        ))),
+        ["f", "cp", min, max] => Ok(Command::PerformFilter(Filter::ChangedByPercent(
+            parse_or_bad_command!(min),
+            Some(parse_or_bad_command!(max)),
+        ))),
+        ["f", "r", start, end] => {
+            let (start, end): (T, T) = (parse_or_bad_command!(start), parse_or_bad_command!(end));
+            if start > end {
+                return Err(BetrayalError::BadCommand(format!(
+                    "range is inverted ({} > {}), try \"f r {} {}\"",
+                    start, end, end, start
+                )));
+            }
+            Ok(Command::PerformFilter(Filter::InRange((start, end))))
+        }
+        ["p", "m", "u32", address, window, levels @ ..] => Ok(Command::PointerMapU32(
+            parse_or_bad_command!(address),
+            parse_window(window)?,
+            parse_levels(levels)?,
+        )),
+        ["p", "m", "u64", address, window, levels @ ..] => Ok(Command::PointerMapU64(
+            parse_or_bad_command!(address),
+            parse_window(window)?,
+            parse_levels(levels)?,
+        )),
+        ["p", "static"] => Ok(Command::StaticPointerChains),
+        ["p", "chains", address] => Ok(Command::PointerChains(parse_or_bad_command!(address))),
+        ["p", "export", path] => Ok(Command::ExportPointerMap(PathBuf::from(path))),
+        ["f3", x, y, z, epsilon] => Ok(Command::PerformFilter(Filter::Triplet(
+            [
+                parse_or_bad_command!(x),
+                parse_or_bad_command!(y),
+                parse_or_bad_command!(z),
+            ],
+            parse_or_bad_command!(epsilon),
+        ))),
+        ["n", window_size, values @ ..] if !values.is_empty() => {
+            Ok(Command::FindNeighbourValues(NeighbourValuesQuery {
+                window_size: parse_or_bad_command!(window_size),
+                values: parse_values(values)?,
+                epsilon: None,
+            }))
+        }
+        ["na", _window_size, _epsilon, ..] if !T::IS_FLOAT => Err(BetrayalError::BadCommand(
+            "tolerance only works in float modes, use \"n\" for integers".to_string(),
+        )),
+        ["na", window_size, epsilon, values @ ..] if !values.is_empty() => {
+            Ok(Command::FindNeighbourValues(NeighbourValuesQuery {
+                window_size: parse_or_bad_command!(window_size),
+                values: parse_values(values)?,
+                epsilon: Some(parse_or_bad_command!(epsilon)),
+            }))
+        }
+        ["resolve", base, offsets @ ..] => Ok(Command::Resolve(
+            base.to_string(),
+            offsets
+                .iter()
+                .map(|offset| {
+                    FromUserInput::from_user_input(offset).ok_or_else(|| {
+                        BetrayalError::BadCommand(format!("invalid offset: {}", offset))
+                    })
+                })
+                .collect::<BetrayalResult<_>>()?,
+        )),
+        ["aob", pattern @ ..] if !pattern.is_empty() => Ok(Command::PerformFilter(
+            Filter::BytePattern(parse_byte_pattern(pattern)?),
+        )),
         _ => Err(BetrayalError::BadCommand("command not found".to_string())),
     }
 }
 
+fn parse_values<T: ReadFromBytes>(values: &[&str]) -> BetrayalResult<Vec<T>> {
+    values
+        .iter()
+        .map(|value| {
+            FromUserInput::from_user_input(value)
+                .ok_or_else(|| BetrayalError::BadCommand(format!("invalid value: {}", value)))
+        })
+        .collect()
+}
+
+/// the optional last argument of "p m"
+fn parse_levels(levels: &[&str]) -> BetrayalResult<usize> {
+    match levels {
+        [] => Ok(PointerMapLimits::default().max_levels),
+        [levels] => Ok(parse_or_bad_command!(levels)),
+        _ => Err(BetrayalError::BadCommand(
+            "expected p m <u32/u64> <address> <window> <levels?>".to_string(),
+        )),
+    }
+}
+
+/// the window argument of "p m"
+fn parse_window<W: FromUserInput + Copy + Into<u64>>(window: &str) -> BetrayalResult<W> {
+    let parsed: W = parse_or_bad_command!(window);
+    check_pointer_window(parsed.into())?;
+    Ok(parsed)
+}
+
+pub fn check_pointer_window(window: u64) -> BetrayalResult<()> {
+    match window {
+        0 => Err(BetrayalError::BadCommand(
+            "the pointer window can't be 0".to_string(),
+        )),
+        window if window > MAX_POINTER_WINDOW => Err(BetrayalError::BadCommand(format!(
+            "the pointer window can be {} bytes at most",
+            MAX_POINTER_WINDOW
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// what's left of `input` after its first `tokens` words and the whitespace character following
+/// them, unlike `split_whitespace` this keeps the spacing (eg. of a "wstr" text) as it was typed
+fn raw_remainder(input: &str, tokens: usize) -> &str {
+    let mut rest = input;
+    for _ in 0..tokens {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+    }
+    let mut chars = rest.chars();
+    chars.next();
+    chars.as_str()
+}
+
+fn parse_bytes(bytes: &[&str]) -> BetrayalResult<Vec<u8>> {
+    bytes
+        .iter()
+        .map(|byte| {
+            u8::from_str_radix(byte, 16)
+                .map_err(|_e| BetrayalError::BadCommand(format!("invalid byte: {}", byte)))
+        })
+        .collect()
+}
+
+fn parse_byte_pattern(pattern: &[&str]) -> BetrayalResult<BytePattern> {
+    pattern
+        .iter()
+        .map(|byte| match *byte {
+            "??" | "?" => Ok(None),
+            byte => u8::from_str_radix(byte, 16).map(Some).map_err(|_e| {
+                BetrayalError::BadCommand(format!("invalid byte in pattern: {}", byte))
+            }),
+        })
+        .collect()
+}
+
 impl<T: ReadFromBytes> FromStr for Command<T> {
     type Err = BetrayalError;
 
@@ -112,6 +486,359 @@ mod test_command_parsing {
         )
     }
 
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn test_watch_writes_command() {
+        assert_eq!(
+            "ww 3".parse::<Command<i32>>().unwrap(),
+            Command::WatchWrites(3)
+        );
+    }
+
+    #[test]
+    fn test_pointer_chains_command() {
+        assert_eq!(
+            "p chains 0x7f00".parse::<Command<i32>>().unwrap(),
+            Command::PointerChains(0x7f00)
+        );
+    }
+
+    #[test]
+    fn test_write_all_commands() {
+        assert_eq!(
+            "wall 100".parse::<Command<i32>>().unwrap(),
+            Command::WriteAll(100)
+        );
+        assert_eq!(
+            "kall 1.5".parse::<Command<f32>>().unwrap(),
+            Command::KeepWritingAll(1.5)
+        );
+    }
+
+    #[test]
+    fn test_near_pointers_command() {
+        assert_eq!(
+            "ptr 0x7f0010 0x100".parse::<Command<f32>>().unwrap(),
+            Command::NearPointers(0x7f0010, 0x100)
+        );
+    }
+
+    #[test]
+    fn test_reinterpret_command() {
+        assert_eq!(
+            "reinterpret u32".parse::<Command<i32>>().unwrap(),
+            Command::Reinterpret("u32".to_string())
+        );
+        assert!("reinterpret i128".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_clear_command() {
+        assert_eq!("clear".parse::<Command<i32>>().unwrap(), Command::Clear);
+    }
+
+    #[test]
+    fn test_dedupe_command() {
+        assert_eq!("dedupe".parse::<Command<i32>>().unwrap(), Command::Dedupe);
+    }
+
+    #[test]
+    fn test_guess_command() {
+        assert_eq!(
+            "guess 0x7f0010".parse::<Command<u8>>().unwrap(),
+            Command::Guess(0x7f0010)
+        );
+    }
+
+    #[test]
+    fn test_maps_command() {
+        assert_eq!("maps".parse::<Command<i32>>().unwrap(), Command::Maps(None));
+        assert_eq!(
+            "maps libc".parse::<Command<i32>>().unwrap(),
+            Command::Maps(Some(String::from("libc")))
+        );
+    }
+
+    #[test]
+    fn test_dump_command() {
+        assert_eq!(
+            "dump 0x1000 64".parse::<Command<i32>>().unwrap(),
+            Command::Dump(0x1000, 64)
+        );
+        assert!("dump 0x1000 0xFFFFFFFFFFFF"
+            .parse::<Command<i32>>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_script_filter() {
+        assert_eq!(
+            "f script value > 100 && address % 8 == 0"
+                .parse::<Command<i32>>()
+                .unwrap(),
+            Command::PerformFilter(Filter::Script(
+                ScriptFilter::new("value > 100 && address % 8 == 0").unwrap()
+            ))
+        );
+        assert!("f script value > 1".parse::<Command<f32>>().is_err());
+    }
+
+    #[test]
+    fn test_greater_less_than_filters() {
+        assert_eq!(
+            "f gt 100".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::GreaterThan(100))
+        );
+        assert_eq!(
+            "f lt 50".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::LessThan(50))
+        );
+    }
+
+    #[test]
+    fn test_changed_by_percent() {
+        assert_eq!(
+            "f cp 10".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::ChangedByPercent(10, None))
+        );
+        assert_eq!(
+            "f cp 10 50".parse::<Command<f32>>().unwrap(),
+            Command::PerformFilter(Filter::ChangedByPercent(10.0, Some(50.0)))
+        );
+    }
+
+    #[test]
+    fn test_range_filter() {
+        assert_eq!(
+            "f r 15 300".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::InRange((15, 300)))
+        );
+        assert!(matches!(
+            "f r 300 15".parse::<Command<i32>>(),
+            Err(BetrayalError::BadCommand(_))
+        ));
+    }
+
+    #[test]
+    fn test_float_only_filters() {
+        assert_eq!(
+            "f nan".parse::<Command<f32>>().unwrap(),
+            Command::PerformFilter(Filter::IsNan)
+        );
+        assert_eq!(
+            "f fin".parse::<Command<f64>>().unwrap(),
+            Command::PerformFilter(Filter::IsFinite)
+        );
+        assert!("f nan".parse::<Command<i32>>().is_err());
+        assert!("f fin".parse::<Command<u8>>().is_err());
+    }
+
+    #[test]
+    fn test_bits_filter() {
+        assert_eq!(
+            "f bits 0xF0 0x50".parse::<Command<u8>>().unwrap(),
+            Command::PerformFilter(Filter::BitsSet(0xF0, 0x50))
+        );
+        assert!("f bits 0xF0 0x50".parse::<Command<f32>>().is_err());
+        let info = crate::AddressInfo { writable: true };
+        let results = Default::default();
+        assert!(Filter::BitsSet(0xF0u8, 0x50).matches((info, 0, 0x5A), &results));
+        assert!(!Filter::BitsSet(0xF0u8, 0x50).matches((info, 0, 0x6A), &results));
+    }
+
+    #[test]
+    fn test_find_copies() {
+        assert_eq!(
+            "f eq @3".parse::<Command<i32>>().unwrap(),
+            Command::FindCopies(3)
+        );
+        assert!("f eq 3".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_mod_filter() {
+        assert_eq!(
+            "f mod 10".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::DivisibleBy(10))
+        );
+        assert!("f mod 10".parse::<Command<f32>>().is_err());
+        assert!("f mod 0".parse::<Command<i32>>().is_err());
+        let info = crate::AddressInfo { writable: true };
+        let results = Default::default();
+        assert!(Filter::DivisibleBy(10).matches((info, 0, 2130), &results));
+        assert!(!Filter::DivisibleBy(10).matches((info, 0, 2137), &results));
+    }
+
+    #[test]
+    fn test_triplet() {
+        assert_eq!(
+            "f3 1.5 20 -3 0.1".parse::<Command<f32>>().unwrap(),
+            Command::PerformFilter(Filter::Triplet([1.5, 20.0, -3.0], 0.1))
+        );
+    }
+
+    #[test]
+    fn test_neighbour_values() {
+        assert_eq!(
+            "n 16 1 2 14".parse::<Command<i32>>().unwrap(),
+            Command::FindNeighbourValues(NeighbourValuesQuery {
+                window_size: 16,
+                values: vec![1, 2, 14],
+                epsilon: None,
+            })
+        );
+        assert!("n 16".parse::<Command<i32>>().is_err());
+        assert_eq!(
+            "na 16 0.1 1.5 20".parse::<Command<f32>>().unwrap(),
+            Command::FindNeighbourValues(NeighbourValuesQuery {
+                window_size: 16,
+                values: vec![1.5, 20.0],
+                epsilon: Some(0.1),
+            })
+        );
+        assert!("na 16 1 2 14".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(
+            r#"resolve static_address(PID,"game")+0x10 0x8 16"#.parse::<Command<i32>>().unwrap(),
+            Command::Resolve(
+                r#"static_address(PID,"game")+0x10"#.to_string(),
+                vec![8, 16]
+            )
+        );
+    }
+
+    #[test]
+    fn test_byte_pattern() {
+        assert_eq!(
+            "aob 48 8B ?? ?? 89".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::BytePattern(vec![
+                Some(0x48),
+                Some(0x8B),
+                None,
+                None,
+                Some(0x89)
+            ]))
+        );
+        assert!("aob 48 XY".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_write_bytes() {
+        assert_eq!(
+            "wbytes 0x1000 48 65 6C".parse::<Command<i32>>().unwrap(),
+            Command::WriteBytes(0x1000, vec![0x48, 0x65, 0x6C])
+        );
+        assert_eq!(
+            "wstr 0x1000 hi there".parse::<Command<i32>>().unwrap(),
+            Command::WriteBytes(0x1000, b"hi there\0".to_vec())
+        );
+        assert_eq!(
+            "wstr 0x1000 a  b\t".parse::<Command<i32>>().unwrap(),
+            Command::WriteBytes(0x1000, b"a  b\t\0".to_vec())
+        );
+        assert!("wbytes 0x1000 ??".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(
+            "name 2 player hp".parse::<Command<i32>>().unwrap(),
+            Command::Label(2, "player hp".to_string())
+        )
+    }
+
+    #[test]
+    fn test_pointer_map_window_and_levels() {
+        assert_eq!(
+            "p m u64 0x1000 0x100 3".parse::<Command<i32>>().unwrap(),
+            Command::PointerMapU64(0x1000, 0x100, 3)
+        );
+        assert_eq!(
+            "p m u32 4096 16".parse::<Command<i32>>().unwrap(),
+            Command::PointerMapU32(4096, 16, PointerMapLimits::default().max_levels)
+        );
+        assert!("p m u64 0x1000 0x100 3 4".parse::<Command<i32>>().is_err());
+        assert!("p m u64 0x1000 0 3".parse::<Command<i32>>().is_err());
+        assert!("p m u32 0x1000 0x10001".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_changed_in() {
+        assert_eq!(
+            "f changed-in libgame.so".parse::<Command<i32>>().unwrap(),
+            Command::ChangedIn("libgame.so".to_string())
+        );
+        assert_eq!(
+            "f changed".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::Changed)
+        );
+        assert!("f changed-in".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_only() {
+        assert_eq!(
+            "only 0x7fff0010".parse::<Command<i32>>().unwrap(),
+            Command::Only(0x7fff0010)
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(
+            "cmp 0 4".parse::<Command<i32>>().unwrap(),
+            Command::Compare(0, 4)
+        );
+        assert!("cmp 0".parse::<Command<i32>>().is_err());
+    }
+
+    #[test]
+    fn test_hex_values() {
+        assert_eq!(
+            "f e 0x1F4".parse::<Command<i32>>().unwrap(),
+            Command::PerformFilter(Filter::IsEqual(500))
+        );
+        assert_eq!(
+            "a 0x7fff0010".parse::<Command<i32>>().unwrap(),
+            Command::AddAddress(0x7fff0010)
+        );
+        assert_eq!(
+            "w 3 0xFF".parse::<Command<u8>>().unwrap(),
+            Command::Write((3, 255))
+        );
+        assert!("f gt 200".parse::<Command<i8>>().is_err());
+        assert!("w 0 300".parse::<Command<i8>>().is_err());
+    }
+
+    #[test]
+    fn test_write_address() {
+        assert_eq!(
+            "wa 0x7fff0010 2137".parse::<Command<i32>>().unwrap(),
+            Command::WriteAddress(0x7fff0010, 2137)
+        )
+    }
+
+    #[test]
+    fn test_page() {
+        assert_eq!("page 3".parse::<Command<i32>>().unwrap(), Command::Page(3));
+        assert_eq!(
+            "page size 100".parse::<Command<i32>>().unwrap(),
+            Command::PageSize(100)
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        assert_eq!(
+            "sort value-desc".parse::<Command<i32>>().unwrap(),
+            Command::Sort(SortOrder::ValueDescending)
+        );
+        assert!("sort size".parse::<Command<i32>>().is_err());
+    }
+
     #[test]
     fn test_quit() {
         assert_eq!("q".parse::<Command<i32>>().unwrap(), Command::Quit,)
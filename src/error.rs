@@ -4,20 +4,39 @@ use thiserror::Error;
 pub enum BetrayalError {
     #[error("Unable to find a process with specified PID")]
     BadPid,
+    #[error("target process has exited")]
+    ProcessGone,
     #[error(transparent)]
     ProcError(#[from] procfs::ProcError),
     #[error("Improper command: {0}")]
     BadCommand(String),
     #[error("Partial read occured - aborting")]
     PartialRead,
+    #[error("permission denied accessing the target's memory, run with sudo or set /proc/sys/kernel/yama/ptrace_scope to 0")]
+    PermissionDenied,
+    /// what was being done and the error `process_vm_readv` / `process_vm_writev` gave
+    #[error("{0} :: {1}")]
+    NixError(String, #[source] nix::Error),
+    #[error("address 0x{0:x} is not mapped")]
+    Unmapped(usize),
+    #[error("read of {1} bytes at 0x{0:x} runs past the end of its mapping")]
+    ReadPastMapping(usize, usize),
     #[error(transparent)]
     Other(#[from] anyhow::Error),
     #[error("memory write resulted in an error {0}")]
     BadWrite(String),
     #[error("problem with the config file :: {0}")]
     ConfigFileError(String),
+    #[error("ptrace failed :: {0} (are you root? see /proc/sys/kernel/yama/ptrace_scope)")]
+    PtraceError(String),
     #[error("script has some error :: {0}")]
     ScriptingError(String),
+    #[error("socket server failed :: {0}")]
+    ServerError(String),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("scan matched more than {0} addresses, use a more specific filter (or --max-results)")]
+    TooManyResults(usize),
 }
 
 pub type BetrayalResult<T> = Result<T, BetrayalError>;
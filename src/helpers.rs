@@ -6,6 +6,45 @@ pub fn windowed<'b, 'a: 'b, T>(collection: &'a Vec<T>, size: usize) -> impl Iter
         .map(move |start| &collection[start..(start + size)])
 }
 
+/// `1.2 GiB`
+pub fn human_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} {}", bytes, UNITS[unit]),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+/// `0x7f00001000  48 65 6C 6C 6F 00 ..  |Hello...|`, 16 bytes per line
+pub fn hexdump(base: usize, bytes: &[u8]) -> String {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(line, chunk)| {
+            let hex = chunk
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|b| match *b {
+                    0x20..=0x7E => *b as char,
+                    _ => '.',
+                })
+                .collect::<String>();
+            format!("0x{:x}  {:<47}  |{}|", base + line * 16, hex, ascii)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[cfg(test)]
 mod test_helpers {
     use itertools::Itertools;
@@ -18,4 +57,24 @@ mod test_helpers {
         assert_eq!(windowed(&vec![1,2,3], 2).collect_vec(), vec![vec![1, 2], vec![2, 3]]);
         assert_eq!(windowed(&vec![1,2,3], 3).collect_vec(), vec![vec![1, 2, 3]]);
     }
+
+    #[test]
+    fn test_human_bytes() {
+        assert_eq!(human_bytes(512), "512 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(1288490189), "1.2 GiB");
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let bytes = b"Hello, hexdump!\x00\x01AB";
+        assert_eq!(
+            hexdump(0x1000, bytes),
+            [
+                "0x1000  48 65 6C 6C 6F 2C 20 68 65 78 64 75 6D 70 21 00  |Hello, hexdump!.|",
+                "0x1010  01 41 42                                         |.AB|",
+            ]
+            .join("\n")
+        );
+    }
 }
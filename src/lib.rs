@@ -0,0 +1,2356 @@
+#![feature(box_syntax)]
+
+pub mod cancel;
+pub mod commands;
+pub mod error;
+pub mod helpers;
+pub mod memory;
+pub mod neighbour_values;
+pub mod process;
+pub mod reclass;
+pub mod server;
+pub mod snapshot;
+#[cfg(target_arch = "x86_64")]
+pub mod watchpoint;
+use crate::memory::{Alignment, BytePattern, ReadFromBytes};
+
+use itertools::Itertools;
+use parking_lot::Mutex;
+use petgraph::data::Build;
+use petgraph::dot::Dot;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::{Dfs, EdgeIndexable};
+use petgraph::Direction;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::mem::size_of;
+use std::ops::DerefMut;
+use std::ops::Index;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+    sync::Arc,
+};
+
+use nix::{
+    errno::Errno,
+    sys::uio::{process_vm_readv, process_vm_writev, IoVec, RemoteIoVec},
+    unistd::Pid,
+};
+
+use error::{BetrayalError, BetrayalResult};
+use procmaps::{self, Map};
+
+use crate::neighbour_values::NeighbourValues;
+
+pub fn read_memory(pid: i32, address: usize, bytes_requested: usize) -> BetrayalResult<Vec<u8>> {
+    let mut buffer = vec![0u8; bytes_requested];
+    let remote = RemoteIoVec {
+        base: address,
+        len: bytes_requested,
+    };
+    let bytes_read = match process_vm_readv(
+        Pid::from_raw(pid),
+        &[IoVec::from_mut_slice(&mut buffer)],
+        &[remote],
+    ) {
+        Ok(bytes_read) => bytes_read,
+        // EFAULT doesn't tell an unmapped address from one that's mapped but not readable
+        Err(error) => {
+            return Err(memory_access_error(
+                error,
+                format!("reading {} bytes at 0x{:x}", bytes_requested, address),
+            ));
+        }
+    };
+
+    if bytes_read != bytes_requested {
+        return Err(BetrayalError::PartialRead);
+    }
+    Ok(buffer)
+}
+
+/// the errors that aren't specific to reading or writing, most importantly the missing ptrace
+/// permission, which otherwise just looks like every address being unreadable
+fn memory_access_error(error: nix::Error, what: String) -> BetrayalError {
+    match error {
+        nix::Error::Sys(Errno::ESRCH) => BetrayalError::ProcessGone,
+        nix::Error::Sys(Errno::EPERM) => BetrayalError::PermissionDenied,
+        error => BetrayalError::NixError(what, error),
+    }
+}
+
+#[cfg(test)]
+mod test_memory_errors {
+    use super::*;
+
+    #[test]
+    fn test_errors_keep_their_cause() {
+        let pid = std::process::id() as i32;
+        let error = read_memory(pid, 0x8, 4).unwrap_err();
+        assert!(matches!(
+            error,
+            BetrayalError::NixError(_, nix::Error::Sys(Errno::EFAULT))
+        ));
+        assert!(error.to_string().starts_with("reading 4 bytes at 0x8 :: "));
+        assert!(matches!(
+            memory_access_error(nix::Error::Sys(Errno::EPERM), String::new()),
+            BetrayalError::PermissionDenied
+        ));
+        let error = memory_access_error(nix::Error::Sys(Errno::EINVAL), "reading".to_string());
+        assert!(matches!(
+            error,
+            BetrayalError::NixError(_, nix::Error::Sys(Errno::EINVAL))
+        ));
+        assert!(error.to_string().starts_with("reading :: "));
+    }
+}
+
+/// reads a pointer as wide as the platform's
+pub fn read_pointer(pid: i32, address: usize) -> BetrayalResult<usize> {
+    let bytes = read_memory(pid, address, size_of::<usize>())?;
+    let pointer = match size_of::<usize>() {
+        4 => u32::read_value(bytes).map(|pointer| pointer as usize),
+        _ => u64::read_value(bytes).map(|pointer| pointer as usize),
+    };
+    pointer.map_err(|_e| BetrayalError::PartialRead)
+}
+
+/// dereferences `base` and adds the first offset, then does the same for every following offset
+pub fn resolve_pointer_chain(pid: i32, base: usize, offsets: &[usize]) -> BetrayalResult<usize> {
+    offsets
+        .iter()
+        .enumerate()
+        .try_fold(base, |address, (index, offset)| {
+            read_pointer(pid, address)
+                .map(|pointer| pointer.wrapping_add(*offset))
+                .map_err(|e| {
+                    BetrayalError::BadCommand(format!(
+                        "bad pointer at 0x{:x} (offset #{}) :: {}",
+                        address, index, e
+                    ))
+                })
+        })
+}
+
+/// `process_vm_readv` won't accept more iovecs than this in a single call
+const MAX_IOVECS: usize = 1024;
+
+/// reads `size` bytes at every address using as few syscalls as possible
+pub fn read_memory_bulk(
+    pid: i32,
+    addresses: &[usize],
+    size: usize,
+) -> Vec<BetrayalResult<Vec<u8>>> {
+    let mut values = Vec::with_capacity(addresses.len());
+    let mut remaining = addresses;
+    while !remaining.is_empty() {
+        let chunk = &remaining[..remaining.len().min(MAX_IOVECS)];
+        let mut buffer = vec![0u8; chunk.len() * size];
+        let remote = chunk
+            .iter()
+            .map(|address| RemoteIoVec {
+                base: *address,
+                len: size,
+            })
+            .collect::<Vec<_>>();
+        let bytes_read = match process_vm_readv(
+            Pid::from_raw(pid),
+            &[IoVec::from_mut_slice(&mut buffer)],
+            &remote,
+        ) {
+            Ok(bytes_read) => bytes_read,
+            // the first address is unreadable, it's read alone below
+            Err(nix::Error::Sys(Errno::EFAULT)) => 0,
+            Err(error) => {
+                // anything else (no permission, the process is gone) fails every read alike
+                values.extend(remaining.iter().map(|address| {
+                    Err(memory_access_error(
+                        error,
+                        format!("reading {} bytes at 0x{:x}", size, address),
+                    ))
+                }));
+                break;
+            }
+        };
+        let complete = bytes_read / size;
+        values.extend(
+            buffer
+                .chunks(size)
+                .take(complete)
+                .map(|value| Ok(value.to_vec())),
+        );
+        if complete < chunk.len() {
+            // the kernel stops at the first entry it can't read, so we read that one alone and move on
+            values.push(read_memory(pid, chunk[complete], size));
+            remaining = &remaining[complete + 1..];
+        } else {
+            remaining = &remaining[chunk.len()..];
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod test_bulk_reads {
+    use super::*;
+
+    #[test]
+    fn test_bulk_read_matches_single_reads() {
+        let pid = std::process::id() as i32;
+        let values = (0..3000u32).collect::<Vec<_>>();
+        let mut addresses = values
+            .iter()
+            .map(|v| v as *const u32 as usize)
+            .collect::<Vec<_>>();
+        addresses.insert(1500, 0); // unreadable entry in the middle of a batch
+        let bulk = read_memory_bulk(pid, &addresses, std::mem::size_of::<u32>());
+        let single = addresses
+            .iter()
+            .map(|address| read_memory(pid, *address, std::mem::size_of::<u32>()))
+            .collect::<Vec<_>>();
+        assert_eq!(bulk.len(), single.len());
+        for (bulk, single) in bulk.iter().zip(single.iter()) {
+            assert_eq!(bulk.as_ref().ok(), single.as_ref().ok());
+        }
+        assert!(bulk[1500].is_err());
+    }
+}
+
+/// `(start, len)` of the reads covering `base..ceiling`, `chunk_size` apart and each one
+/// `overlap` bytes longer so that values straddling a boundary aren't missed
+fn scan_chunks(
+    base: usize,
+    ceiling: usize,
+    chunk_size: usize,
+    overlap: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    (base..ceiling)
+        .step_by(chunk_size)
+        .map(move |start| (start, (chunk_size + overlap).min(ceiling - start)))
+}
+
+/// reads `base..ceiling` one `SCAN_CHUNK_SIZE` at a time, yielding the start of each chunk,
+/// the end of the part it owns and its memory (which runs `overlap` bytes further),
+/// anything starting at or past that end belongs to the next chunk
+pub fn read_chunks(
+    pid: i32,
+    base: usize,
+    ceiling: usize,
+    overlap: usize,
+) -> impl Iterator<Item = (usize, usize, BetrayalResult<Vec<u8>>)> {
+    scan_chunks(base, ceiling, SCAN_CHUNK_SIZE, overlap).map(move |(start, len)| {
+        (
+            start,
+            (start + SCAN_CHUNK_SIZE).min(ceiling),
+            read_memory(pid, start, len),
+        )
+    })
+}
+
+/// cheap fingerprint of a chunk's contents, see `ProcessQuery::changed_regions`
+fn chunk_checksum(memory: &[u8]) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    memory.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test_scan_chunks {
+    use super::*;
+
+    #[test]
+    fn test_chunks_overlap_and_stop_at_ceiling() {
+        assert_eq!(
+            scan_chunks(0x1000, 0x1000 + 40, 16, 3).collect::<Vec<_>>(),
+            vec![(0x1000, 19), (0x1010, 19), (0x1020, 8)]
+        );
+        assert_eq!(scan_chunks(0x1000, 0x1000, 16, 3).count(), 0);
+    }
+
+    #[test]
+    fn test_value_straddling_a_boundary_is_found_once() {
+        let pid = std::process::id() as i32;
+        let mut memory = vec![0u8; SCAN_CHUNK_SIZE * 2 + 0x1000];
+        let begin = memory.as_mut_ptr() as usize;
+        let (_info, map) = ProcessQuery::<u32>::mappings_all(pid)
+            .unwrap()
+            .into_iter()
+            .find(|(_info, map)| map.base <= begin && begin < map.ceiling)
+            .unwrap();
+        // chunks are counted from the start of the map, not of the buffer
+        let boundary = (1..)
+            .map(|n| map.base + n * SCAN_CHUNK_SIZE)
+            .find(|boundary| boundary - 2 >= begin)
+            .unwrap();
+        assert!(boundary + 2 <= begin + memory.len());
+        let value = 0x5ca1ab1e_u32;
+        for (offset, byte) in value.to_ne_bytes().iter().enumerate() {
+            unsafe { std::ptr::write_volatile((boundary - 2 + offset) as *mut u8, *byte) };
+        }
+        let options = ScanOptions {
+            alignment: Alignment::Byte,
+            ..Default::default()
+        };
+        let found = find_equal_to_with(pid, value, &options).unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .filter(|(_info, address, _value)| *address == boundary - 2)
+                .count(),
+            1
+        );
+        drop(memory);
+    }
+}
+
+pub fn write_memory(pid: i32, address: usize, buffer: Vec<u8>) -> BetrayalResult<()> {
+    let bytes_requested = buffer.len();
+    let remote = RemoteIoVec {
+        base: address,
+        len: bytes_requested,
+    };
+    match process_vm_writev(Pid::from_raw(pid), &[IoVec::from_slice(&buffer)], &[remote]) {
+        Ok(bytes_written) => {
+            if bytes_written != bytes_requested {
+                return Err(BetrayalError::BadWrite(format!(
+                    "bad write length: {} != {}",
+                    bytes_written, bytes_requested
+                )));
+            } else {
+                Ok(())
+            }
+        }
+        Err(nix::Error::Sys(Errno::EFAULT)) => Err(BetrayalError::BadWrite(format!(
+            "0x{:x} is not mapped or not writable",
+            address
+        ))),
+        Err(e) => Err(memory_access_error(
+            e,
+            format!("writing {} bytes at 0x{:x}", bytes_requested, address),
+        )),
+    }
+}
+
+pub type AddressValue<T: ReadFromBytes> = (AddressInfo, usize, T);
+
+// #[derive(Debug)]
+// pub enum AddressValueAs {
+//     I32(AddressValue<i32>),
+// }
+
+pub type CurrentQueryResults<T: ReadFromBytes> = BTreeMap<usize, AddressValue<T>>;
+
+/// how many scans back can be undone
+pub const MAX_HISTORY: usize = 16;
+
+/// order of displayed results, indices typed in by the user always follow it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Address,
+    Value,
+    ValueDescending,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self::Address
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = BetrayalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "addr" => Ok(Self::Address),
+            "value" => Ok(Self::Value),
+            "value-desc" => Ok(Self::ValueDescending),
+            other => Err(BetrayalError::BadCommand(format!(
+                "sort must be one of addr, value or value-desc, got {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// how many results are displayed at once unless configured otherwise
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// how many writes in a row a freeze can fail before it's stopped, unless configured otherwise
+pub const DEFAULT_FREEZE_RETRIES: usize = 20;
+
+/// regions are scanned this much at a time, so that a huge map doesn't have to fit in memory
+pub const SCAN_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct ProcessQuery<T: ReadFromBytes> {
+    pub pid: i32,
+    pub results: CurrentQueryResults<T>,
+    pub mappings: Vec<(AddressInfo, Map)>,
+    pub alignment: Alignment,
+    /// snapshots of `results` from before each scan, newest last
+    pub history: Vec<CurrentQueryResults<T>>,
+    /// `results` from before the last `update_results`, for displaying deltas
+    pub previous_results: CurrentQueryResults<T>,
+    /// results the last plain refresh couldn't read, they show their last value until pruned
+    pub unreadable: BTreeSet<usize>,
+    /// user given names for addresses
+    pub labels: BTreeMap<usize, String>,
+    /// display values as hex (integers only)
+    pub hex_display: bool,
+    /// most recently built pointer map
+    pub pointer_map: Option<PointerMap>,
+    /// don't report scan progress
+    pub quiet: bool,
+    /// only scan maps whose file path contains this
+    pub region: Option<String>,
+    /// value scans also look at read-only maps, they are skipped by default
+    pub scan_readonly: bool,
+    /// currently displayed page of results
+    pub page: usize,
+    /// how many results are displayed at once
+    pub page_size: usize,
+    /// order in which results are displayed (and indexed)
+    pub sort: SortOrder,
+    /// static, changed and read-only results are displayed in color
+    pub color: bool,
+    /// consecutive failed writes a freeze survives before it's stopped
+    pub freeze_retries: usize,
+    /// a fresh scan matching more addresses than this is aborted instead of eating all the memory
+    pub max_results: Option<usize>,
+    /// "f unknown" checksums every chunk it snapshots, after that refreshes read each chunk holding
+    /// results in one go and only decode the ones whose checksum changed, instead of reading
+    /// every result on its own (worth it while there are millions of results, not once they're few)
+    pub changed_regions: bool,
+    /// chunk start (see `read_chunks`) -> checksum of its contents when its results were last read,
+    /// kept up to date by every refresh and dropped whenever results are replaced
+    pub region_checksums: Option<BTreeMap<usize, u64>>,
+    /// region of the "f changed-in" snapshot the results come from, the next "f changed-in"
+    /// for the same region narrows them down instead of taking a new one
+    pub changed_in: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter<T: ReadFromBytes> {
+    IsEqual(T),
+    /// value and epsilon, for integers this is just a +/- window (epsilon 0 is an exact match)
+    IsApproximately(T, T),
+    InRange((T, T)),
+    GreaterThan(T),
+    LessThan(T),
+    Any,
+    ChangedBy(T),
+    /// minimum and optional maximum change in percent of the previous value,
+    /// a previous value of 0 counts as an infinitely large change (unless the value is still 0)
+    ChangedByPercent(T, Option<T>),
+    Increased,
+    Decreased,
+    Unchanged,
+    /// changed by any amount
+    Changed,
+    /// floats only, the parser rejects it for integers
+    IsNan,
+    /// floats only, the parser rejects it for integers
+    IsFinite,
+    /// mask and expected bits, integers only, the parser rejects it for floats
+    BitsSet(T, T),
+    /// integers only, the parser rejects it for floats
+    DivisibleBy(T),
+    InAddressRanges(Vec<(usize, usize)>),
+    BytePattern(BytePattern),
+    /// three consecutive values (eg. a position) and the tolerance for each of them
+    Triplet([T; 3], T),
+    /// rhai expression over `value` and `address`, integers only, the parser rejects it for floats
+    Script(reclass::scripting::ScriptFilter),
+}
+
+pub type Writer<T: ReadFromBytes> = (usize, T);
+
+/// a thread that keeps writing `value` to a single address until stopped
+#[derive(Debug)]
+pub struct FreezeHandle<T: ReadFromBytes> {
+    pub value: T,
+    pub stop: Arc<AtomicBool>,
+    pub thread: JoinHandle<()>,
+}
+
+impl<T: ReadFromBytes> FreezeHandle<T> {
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if self.thread.join().is_err() {
+            eprintln!(" :: [ERR] :: writer thread panicked");
+        }
+    }
+}
+
+/// active freezes keyed by address
+pub type FreezeRegistry<T> = Arc<Mutex<HashMap<usize, FreezeHandle<T>>>>;
+
+impl<T: ReadFromBytes> Filter<T> {
+    pub fn matches(
+        self,
+        result: AddressValue<T>,
+        current_results: &CurrentQueryResults<T>,
+    ) -> bool {
+        let (info, address, current_value) = result;
+        match self {
+            Self::IsEqual(v) => v == current_value,
+            Self::IsApproximately(v, epsilon) => {
+                memory::approximately_equal(current_value, v, epsilon)
+            }
+            Self::InRange((base, ceiling)) => base <= current_value && current_value <= ceiling,
+            Self::GreaterThan(v) => current_value > v,
+            Self::LessThan(v) => current_value < v,
+            Self::Any => true,
+            Self::ChangedBy(diff) => current_results
+                .get(&address)
+                // .find(|(candidate_address, _value)| address == *candidate_address)
+                .map(|(_info, _a, value)| current_value + diff == *value)
+                .unwrap_or(false),
+            Self::ChangedByPercent(min, max) => current_results
+                .get(&address)
+                .map(|(_info, _a, previous)| {
+                    let (previous, current) = (previous.to_f64(), current_value.to_f64());
+                    let percent = if previous != 0.0 {
+                        ((current - previous) / previous).abs() * 100.0
+                    } else if current != 0.0 {
+                        f64::INFINITY
+                    } else {
+                        0.0
+                    };
+                    percent >= min.to_f64()
+                        && max.map(|max| percent <= max.to_f64()).unwrap_or(true)
+                })
+                .unwrap_or(false),
+            Self::Increased => current_results
+                .get(&address)
+                .map(|(_info, _a, value)| current_value > *value)
+                .unwrap_or(false),
+            Self::Decreased => current_results
+                .get(&address)
+                .map(|(_info, _a, value)| current_value < *value)
+                .unwrap_or(false),
+            Self::Unchanged => current_results
+                .get(&address)
+                .map(|(_info, _a, value)| current_value == *value)
+                .unwrap_or(false),
+            Self::Changed => current_results
+                .get(&address)
+                .map(|(_info, _a, value)| current_value != *value)
+                .unwrap_or(false),
+            Self::IsNan => current_value.value_is_nan(),
+            Self::IsFinite => current_value.value_is_finite(),
+            Self::BitsSet(mask, expected) => current_value.masked(mask) == expected,
+            Self::DivisibleBy(divisor) => current_value.divisible_by(divisor),
+            Self::InAddressRanges(ranges) => ranges
+                .iter()
+                .any(|(base, ceiling)| base <= &address && &address <= ceiling),
+            Self::BytePattern(_) => true, // bytes are matched against raw memory in `query`
+            Self::Triplet(..) => true,    // same as above
+            Self::Script(script) => script.matches(address, current_value.to_i64()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressInfo {
+    pub writable: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StaticLocation {
+    pub map_path: String,
+    pub offset: usize,
+    pub base: usize,
+}
+
+impl std::fmt::Display for StaticLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "static_address(PID,\"{}\")+{}",
+            self.map_path, self.offset
+        )
+    }
+}
+
+/// pointer path starting at a static address, each step dereferences and then adds the offset
+#[derive(Debug, Clone)]
+pub struct StaticPointerChain {
+    pub location: StaticLocation,
+    pub offsets: Vec<usize>,
+}
+
+impl std::fmt::Display for StaticPointerChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.location)?;
+        for offset in self.offsets.iter() {
+            write!(f, " + [{}]", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl AddressInfo {
+    pub fn from_address<T: memory::ReadFromBytes>(
+        process: &ProcessQuery<T>,
+        pid: i32,
+        address: usize,
+    ) -> BetrayalResult<Self> {
+        let (info, _map) = process
+            .mappings()?
+            .into_iter()
+            .find(|(_info, map)| map.base <= address && address < map.ceiling)
+            .ok_or(BetrayalError::PartialRead)?;
+        Ok(info.clone())
+    }
+
+    pub fn is_static(&self) -> bool {
+        !self.writable
+    }
+
+    /// file with permission RW, either with a name, or directly following a named map (without a gap!!)
+    pub fn static_location(&self, pid: i32, address: usize) -> Option<StaticLocation> {
+        if !self.writable {
+            return None;
+        }
+        file_location(pid, address)
+    }
+}
+
+/// `address` as an offset from the start of the file it's mapped from (or of the named map
+/// directly preceding it, for a .bss), whatever the permissions of its map
+pub fn file_location(pid: i32, address: usize) -> Option<StaticLocation> {
+    use procmaps::Path;
+    let maps = ProcessQuery::<u8>::mappings_all_with_unreadable(pid).ok()?;
+    let slice_index = match maps
+        .iter()
+        .find_position(|(_info, map)| map.base <= address && address < map.ceiling)
+    {
+        Some((map_index, (_, map))) => match &map.pathname {
+            Path::MappedFile(name)
+                if name == "" && map.base == maps.get(map_index - 1)?.1.ceiling =>
+            {
+                // this is a .bss, we start one address up
+                Some(map_index - 1)
+            }
+            Path::MappedFile(_) => Some(map_index), // this is a normal mapped file, still we need to offset it to allow for lookups
+            _ => None,
+        },
+        None => None,
+    }?;
+
+    let maps = (&maps[..(slice_index + 1)]) // omit later entries
+        .iter()
+        .sorted_by_key(|(_, m)| m.base)
+        .rev() // go backwards
+        .collect::<Vec<_>>();
+    let static_base = maps
+        .iter()
+        .zip(maps.iter().skip(1)) // compare neighbours
+        .inspect(|((_, curr), (_, next))| {
+            // println!(
+            //     "({:x} - {:x}) :: {:?}\n({:x} - {:x}) :: {:?}",
+            //     curr.base, curr.ceiling, curr.pathname, next.base, next.ceiling, next.pathname
+            // )
+        })
+        .take_while(|((_, curr), (_, next))| curr.base == next.ceiling) // there can be no memory gap
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|((_, curr), (_, _))| curr)
+        .group_by(|m| &m.pathname) // chunks of maps with the same path
+        .into_iter()
+        .next()
+        .map(|(_, v)| v)?
+        .last()?;
+
+    let path = &static_base.pathname;
+    match path {
+        procmaps::Path::MappedFile(path) => Some(StaticLocation {
+            map_path: path.clone(),
+            base: static_base.base,
+            offset: address - static_base.base,
+        }),
+        _ => None,
+    }
+}
+
+/// file path of a map, or `[Heap]`, `[Stack]` etc. for the special ones
+pub fn map_name(map: &Map) -> String {
+    match &map.pathname {
+        procmaps::Path::MappedFile(path) => path.clone(),
+        other => format!("[{:?}]", other),
+    }
+}
+
+impl From<&Map> for AddressInfo {
+    fn from(m: &Map) -> Self {
+        Self {
+            writable: m.perms.writable,
+        }
+    }
+}
+
+/// what the one-off scans (`find_equal_to_with` etc.) look at,
+/// the default matches a fresh interactive session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanOptions {
+    pub alignment: Alignment,
+    /// only scan maps whose file path contains this
+    pub region: Option<String>,
+    /// skip read-only maps
+    pub writable_only: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            alignment: Default::default(),
+            region: None,
+            writable_only: true,
+        }
+    }
+}
+
+impl ScanOptions {
+    fn process_query<T: ReadFromBytes>(&self, pid: i32) -> ProcessQuery<T> {
+        let mut process = ProcessQuery::<T>::new(pid);
+        process.alignment = self.alignment;
+        process.region = self.region.clone();
+        process.scan_readonly = !self.writable_only;
+        process
+    }
+
+    fn scan<T: ReadFromBytes>(
+        &self,
+        pid: i32,
+        filter: Filter<T>,
+    ) -> BetrayalResult<Vec<AddressValue<T>>> {
+        let mut process = self.process_query(pid);
+        process.perform_new_query(filter)?;
+        Ok(process.results.into_iter().map(|(_k, v)| v).collect())
+    }
+}
+
+pub fn find_equal_to<T: ReadFromBytes>(pid: i32, value: T) -> BetrayalResult<Vec<AddressValue<T>>> {
+    find_equal_to_with(pid, value, &ScanOptions::default())
+}
+
+pub fn find_equal_to_with<T: ReadFromBytes>(
+    pid: i32,
+    value: T,
+    options: &ScanOptions,
+) -> BetrayalResult<Vec<AddressValue<T>>> {
+    options.scan(pid, Filter::IsEqual(value))
+}
+
+pub fn find_in_range<T: ReadFromBytes>(
+    pid: i32,
+    min: T,
+    max: T,
+) -> BetrayalResult<Vec<AddressValue<T>>> {
+    find_in_range_with(pid, min, max, &ScanOptions::default())
+}
+
+pub fn find_in_range_with<T: ReadFromBytes>(
+    pid: i32,
+    min: T,
+    max: T,
+    options: &ScanOptions,
+) -> BetrayalResult<Vec<AddressValue<T>>> {
+    options.scan(pid, Filter::InRange((min, max)))
+}
+
+/// addresses anywhere in readable memory holding a pointer to at most `slack` bytes before `target`,
+/// with the offset from the pointer to `target`
+pub fn find_near_pointers(
+    pid: i32,
+    target: usize,
+    slack: usize,
+) -> BetrayalResult<Vec<(usize, usize)>> {
+    let options = ScanOptions {
+        writable_only: false,
+        ..Default::default()
+    };
+    Ok(find_in_range_with(
+        pid,
+        target.saturating_sub(slack) as u64,
+        target as u64,
+        &options,
+    )?
+    .into_iter()
+    .map(|(_info, address, pointer)| (address, target - pointer as usize))
+    .sorted()
+    .collect())
+}
+
+#[cfg(test)]
+mod test_scan_options {
+    use super::*;
+
+    #[test]
+    fn test_region_limits_the_scan() {
+        let pid = std::process::id() as i32;
+        let options = ScanOptions {
+            region: Some("no such file, hopefully".to_string()),
+            ..Default::default()
+        };
+        assert!(find_in_range_with(pid, i32::MIN, i32::MAX, &options)
+            .unwrap()
+            .is_empty());
+    }
+}
+
+use petgraph::graph::DiGraph;
+
+pub fn log_graph<T: ReadFromBytes + Serialize + TryFrom<usize>>(graph: &DiGraph<T, ()>, pid: i32) {
+    for edge in graph.node_indices() {
+        print!("[*]");
+        let mut dfs = petgraph::visit::Dfs::new(&graph, edge);
+
+        while let Some(visited) = dfs.next(&graph) {
+            let address = graph[visited];
+            print!(" -> {:?}", address);
+        }
+        println!();
+    }
+
+    println!();
+}
+
+#[derive(Debug, Clone)]
+pub enum PointerMap {
+    U32(DiGraph<u32, ()>),
+    U64(DiGraph<u64, ()>),
+}
+
+impl PointerMap {
+    /// graphviz representation of the map
+    pub fn to_dot(&self) -> String {
+        let config = &[petgraph::dot::Config::EdgeNoLabel];
+        match self {
+            Self::U32(graph) => format!("{:?}", Dot::with_config(graph, config)),
+            Self::U64(graph) => format!("{:?}", Dot::with_config(graph, config)),
+        }
+    }
+
+    pub fn static_chains(&self, pid: i32) -> BetrayalResult<Vec<StaticPointerChain>> {
+        match self {
+            Self::U32(graph) => static_pointer_chains(pid, graph),
+            Self::U64(graph) => static_pointer_chains(pid, graph),
+        }
+    }
+
+    /// every path in the map that ends at `address`, outermost pointer first
+    pub fn chains_to(&self, address: usize) -> Vec<Vec<usize>> {
+        fn addresses<T: Copy + Into<u64>>(
+            graph: &DiGraph<T, ()>,
+            address: usize,
+        ) -> Vec<Vec<usize>> {
+            chains_to(graph, address as u64)
+                .into_iter()
+                .map(|chain| {
+                    chain
+                        .into_iter()
+                        .map(|a| Into::<u64>::into(a) as usize)
+                        .collect()
+                })
+                .collect()
+        }
+        match self {
+            Self::U32(graph) => addresses(graph, address),
+            Self::U64(graph) => addresses(graph, address),
+        }
+    }
+}
+
+/// depth first walk against the edges, from every node holding `address` to the pointers nothing points to
+pub fn chains_to<T: Copy + Into<u64>>(graph: &DiGraph<T, ()>, address: u64) -> Vec<Vec<T>> {
+    let mut chains = vec![];
+    let mut stack = graph
+        .node_indices()
+        .filter(|node| Into::<u64>::into(graph[*node]) == address)
+        .map(|node| vec![node])
+        .collect::<Vec<_>>();
+    while let Some(path) = stack.pop() {
+        let last = *path.last().expect("paths are never empty");
+        let mut pointers = graph
+            .neighbors_directed(last, Direction::Incoming)
+            .filter(|pointer| !path.contains(pointer))
+            .peekable();
+        if pointers.peek().is_none() {
+            chains.push(path.iter().rev().map(|node| graph[*node]).collect());
+            continue;
+        }
+        for pointer in pointers {
+            let mut path = path.clone();
+            path.push(pointer);
+            stack.push(path);
+        }
+    }
+    chains
+}
+
+/// walks from every pointer that nothing else points to down to the searched address,
+/// keeping only the paths that start in a file-backed map (see `file_location`)
+pub fn static_pointer_chains<T: ReadFromBytes + TryInto<usize>>(
+    pid: i32,
+    graph: &DiGraph<T, ()>,
+) -> BetrayalResult<Vec<StaticPointerChain>> {
+    let chain_offsets = |start: NodeIndex| -> Option<Vec<usize>> {
+        let mut offsets = vec![];
+        let mut current = start;
+        while let Some(target) = graph
+            .neighbors_directed(current, Direction::Outgoing)
+            .next()
+        {
+            let pointer = read_memory(pid, graph[current].try_into().ok()?, size_of::<T>()).ok()?;
+            let pointer: usize = T::read_value(pointer).ok()?.try_into().ok()?;
+            let target_address: usize = graph[target].try_into().ok()?;
+            offsets.push(target_address.checked_sub(pointer)?);
+            current = target;
+        }
+        Some(offsets)
+    };
+
+    Ok(graph
+        .externals(Direction::Incoming)
+        .filter_map(|start| {
+            let address: usize = graph[start].try_into().ok()?;
+            // a pointer kept in .rodata / .data.rel.ro is just as static as one in .data
+            let location = file_location(pid, address)?;
+            let offsets = chain_offsets(start)?;
+            if offsets.is_empty() {
+                return None; // that's just the searched address
+            }
+            Some(StaticPointerChain { location, offsets })
+        })
+        .collect())
+}
+
+/// upper bounds for pointer map building, without them branchy processes spawn thousands of threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointerMapLimits {
+    /// nodes in the whole graph
+    pub max_nodes: usize,
+    /// how many pointers deep a chain can go
+    pub max_levels: usize,
+}
+
+impl Default for PointerMapLimits {
+    fn default() -> Self {
+        Self {
+            max_nodes: 100_000,
+            max_levels: 5,
+        }
+    }
+}
+
+/// adds `addresses` to the tree and recurses into whatever `find_pointers` returns for them,
+/// parallelism is capped by rayon's thread pool instead of spawning a thread per node
+pub fn build_pointer_tree<T, F>(
+    tree: &Mutex<DiGraph<T, ()>>,
+    current: Option<NodeIndex>,
+    addresses: Vec<T>,
+    level: usize,
+    limits: PointerMapLimits,
+    nodes: &AtomicUsize,
+    find_pointers: &F,
+) -> BetrayalResult<()>
+where
+    T: ReadFromBytes,
+    F: Fn(T) -> BetrayalResult<Vec<T>> + Sync,
+{
+    addresses.into_par_iter().try_for_each(|address| {
+        cancel::check()?;
+        if nodes.fetch_add(1, Ordering::Relaxed) >= limits.max_nodes {
+            return Ok(());
+        }
+        let a = {
+            let mut tree = tree.lock();
+            let a = tree.add_node(address);
+            if let Some(current) = current {
+                tree.add_edge(a, current, ());
+            }
+            a
+        };
+        if level >= limits.max_levels {
+            return Ok(());
+        }
+
+        let addresses = find_pointers(address)?;
+        build_pointer_tree(
+            tree,
+            Some(a),
+            addresses,
+            level + 1,
+            limits,
+            nodes,
+            find_pointers,
+        )
+    })
+}
+
+/// builds a graph of pointers leading to `address` using a custom pointer lookup
+pub fn pointer_map_with<T, F>(
+    address: T,
+    limits: PointerMapLimits,
+    find_pointers: F,
+) -> BetrayalResult<DiGraph<T, ()>>
+where
+    T: ReadFromBytes,
+    F: Fn(T) -> BetrayalResult<Vec<T>> + Sync,
+{
+    let graph = Mutex::new(DiGraph::new());
+    let nodes = AtomicUsize::new(0);
+    build_pointer_tree(
+        &graph,
+        None,
+        vec![address],
+        0,
+        limits,
+        &nodes,
+        &find_pointers,
+    )?;
+    if nodes.load(Ordering::Relaxed) > limits.max_nodes {
+        println!(
+            " :: [WARN] :: pointer map truncated at {} nodes",
+            limits.max_nodes
+        );
+    }
+    Ok(graph.into_inner())
+}
+
+/// lowest value that still counts as a pointer to `address`, the window can't reach below 0
+fn window_start<T: ReadFromBytes>(address: T, window: T) -> T {
+    if window > address {
+        T::default()
+    } else {
+        address - window
+    }
+}
+
+/// graph of pointers leading to `address`: a value counts as a pointer when it's at most `window`
+/// bytes before the address it leads to (eg. a pointer to the struct a field is in), and chains
+/// go at most `levels` pointers deep
+pub fn pointer_map<T: 'static + ReadFromBytes + Serialize + TryFrom<usize>>(
+    pid: i32,
+    address: T,
+    window: T,
+    levels: usize,
+) -> BetrayalResult<DiGraph<T, ()>> {
+    let limits = PointerMapLimits {
+        max_levels: levels,
+        ..Default::default()
+    };
+    // static chains often start in read-only maps (.data.rel.ro, vtables)
+    let options = ScanOptions {
+        writable_only: false,
+        ..Default::default()
+    };
+    pointer_map_with(address, limits, |address| {
+        Ok(
+            find_in_range_with(pid, window_start(address, window), address, &options)?
+                .into_iter()
+                .filter_map(|(_, a, _)| a.try_into().ok())
+                .collect(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod test_pointer_map {
+    use super::*;
+
+    /// the thread-per-node implementation this was ported from
+    fn spawning_pointer_tree(
+        tree: Arc<Mutex<DiGraph<u64, ()>>>,
+        current: Option<NodeIndex>,
+        addresses: Vec<u64>,
+        level: usize,
+        limits: PointerMapLimits,
+    ) {
+        let mut tasks = vec![];
+        for address in addresses {
+            let tree = Arc::clone(&tree);
+            let a = {
+                let mut tree = tree.lock();
+                let a = tree.add_node(address);
+                if let Some(current) = current {
+                    tree.add_edge(a, current, ());
+                }
+                a
+            };
+            if level >= limits.max_levels {
+                continue;
+            }
+            let addresses = synthetic_pointers(address);
+            tasks.push(std::thread::spawn(move || {
+                spawning_pointer_tree(tree, Some(a), addresses, level + 1, limits)
+            }));
+        }
+        for task in tasks {
+            task.join().unwrap();
+        }
+    }
+
+    fn synthetic_pointers(address: u64) -> Vec<u64> {
+        (1..=3).map(|i| address * 10 + i).collect()
+    }
+
+    fn nodes(graph: &DiGraph<u64, ()>) -> Vec<u64> {
+        graph
+            .raw_nodes()
+            .iter()
+            .map(|node| node.weight)
+            .sorted()
+            .collect()
+    }
+
+    fn edges(graph: &DiGraph<u64, ()>) -> Vec<(u64, u64)> {
+        graph
+            .raw_edges()
+            .iter()
+            .map(|edge| (graph[edge.source()], graph[edge.target()]))
+            .sorted()
+            .collect()
+    }
+
+    #[test]
+    fn test_chains_to() {
+        let graph = pointer_map_with(
+            7,
+            PointerMapLimits {
+                max_nodes: usize::MAX,
+                max_levels: 2,
+            },
+            |address| Ok(synthetic_pointers(address)),
+        )
+        .unwrap();
+        assert_eq!(
+            chains_to(&graph, 71)
+                .into_iter()
+                .sorted()
+                .collect::<Vec<_>>(),
+            vec![vec![711, 71], vec![712, 71], vec![713, 71]]
+        );
+        let chains = chains_to(&graph, 7);
+        assert_eq!(chains.len(), 9);
+        assert!(chains.iter().all(|chain| chain.len() == 3 && chain[2] == 7));
+        assert_eq!(chains_to(&graph, 712), vec![vec![712]]);
+        assert!(chains_to(&graph, 1234).is_empty());
+    }
+
+    #[test]
+    fn test_pointer_map_matches_spawning_implementation() {
+        let limits = PointerMapLimits {
+            max_nodes: usize::MAX,
+            max_levels: 4,
+        };
+        let expected: Arc<Mutex<DiGraph<u64, ()>>> = Default::default();
+        spawning_pointer_tree(Arc::clone(&expected), None, vec![7], 0, limits);
+        let expected = expected.lock().clone();
+
+        let graph = pointer_map_with(7, limits, |address| Ok(synthetic_pointers(address))).unwrap();
+
+        assert_eq!(graph.node_count(), expected.node_count());
+        assert_eq!(nodes(&graph), nodes(&expected));
+        assert_eq!(edges(&graph), edges(&expected));
+    }
+
+    #[test]
+    fn test_static_chains_can_start_in_read_only_maps() {
+        let pid = std::process::id() as i32;
+        let target = &TARGET as *const u64 as u64;
+        let pointer = &READ_ONLY_POINTER as *const &u64 as u64;
+        let mut graph = DiGraph::<u64, ()>::new();
+        let (from, to) = (graph.add_node(pointer), graph.add_node(target));
+        graph.add_edge(from, to, ());
+        let chains = static_pointer_chains(pid, &graph).unwrap();
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].offsets, vec![0]);
+        let location = &chains[0].location;
+        assert_eq!(location.base + location.offset, pointer as usize);
+    }
+
+    static TARGET: u64 = 2137;
+    /// needs a relocation, so it ends up in .data.rel.ro which is read-only once relocated
+    static READ_ONLY_POINTER: &u64 = &TARGET;
+
+    #[test]
+    fn test_pointers_in_read_only_maps_are_found() {
+        let pid = std::process::id() as i32;
+        let target = &TARGET as *const u64 as u64;
+        let pointer = &READ_ONLY_POINTER as *const &u64 as usize;
+        assert_eq!(*READ_ONLY_POINTER, 2137);
+        let (info, _map) = ProcessQuery::<u64>::mappings_all(pid)
+            .unwrap()
+            .into_iter()
+            .find(|(_info, map)| map.base <= pointer && pointer < map.ceiling)
+            .unwrap();
+        assert!(!info.writable);
+
+        let graph = pointer_map::<u64>(pid, target, 0, 1).unwrap();
+        assert!(nodes(&graph).contains(&(pointer as u64)));
+    }
+
+    #[test]
+    fn test_window_bigger_than_the_address() {
+        assert_eq!(window_start(0x10u64, 0x100), 0);
+        assert_eq!(window_start(0x10u32, 0x10), 0);
+        assert_eq!(window_start(0x1000u64, 0x100), 0xf00);
+    }
+}
+
+impl<T: ReadFromBytes> ProcessQuery<T> {
+    pub fn new(pid: i32) -> Self {
+        Self {
+            pid,
+            results: Default::default(),
+            mappings: Default::default(),
+            alignment: Default::default(),
+            history: Default::default(),
+            previous_results: Default::default(),
+            unreadable: Default::default(),
+            labels: Default::default(),
+            hex_display: false,
+            pointer_map: None,
+            quiet: false,
+            region: None,
+            scan_readonly: false,
+            page: 0,
+            page_size: DEFAULT_PAGE_SIZE,
+            sort: Default::default(),
+            color: false,
+            freeze_retries: DEFAULT_FREEZE_RETRIES,
+            max_results: None,
+            changed_regions: false,
+            region_checksums: None,
+            changed_in: None,
+        }
+    }
+
+    pub fn read_at(&mut self, pid: i32, address: usize) -> BetrayalResult<AddressValue<T>> {
+        if self.mappings.is_empty() {
+            self.update_mappings()?; // oof
+        }
+        let (info, _map) = self
+            .mappings()?
+            .into_iter()
+            .find(|(info, m)| m.base <= address && address < m.ceiling)
+            .ok_or(BetrayalError::PartialRead)?;
+        let val = read_memory(pid, address, std::mem::size_of::<T>())?;
+        Ok((
+            info.clone(),
+            address,
+            T::read_value(val).map_err(|_e| BetrayalError::PartialRead)?,
+        ))
+    }
+
+    pub fn write_at(pid: i32, address: usize, value: T) -> BetrayalResult<()> {
+        let mut buffer = vec![];
+        value
+            .write_bytes(&mut buffer)
+            .map_err(|e| BetrayalError::BadWrite(format!("bad write: {}", e)))?;
+        write_memory(pid, address, buffer)?;
+        Ok(())
+    }
+
+    /// re-reads every result, addresses that can't be read anymore are dropped
+    pub fn update_results(&mut self) -> BetrayalResult<()> {
+        self.refresh_results(true).map(drop)
+    }
+
+    /// same as `update_results`, returns how many addresses were dropped
+    pub fn update_results_pruning(&mut self) -> BetrayalResult<usize> {
+        self.refresh_results(true)
+    }
+
+    /// the plain refresh command: addresses that can't be read (right now) keep their last value
+    /// and end up in `unreadable`, returns how many
+    pub fn update_results_keeping_unreadable(&mut self) -> BetrayalResult<usize> {
+        self.refresh_results(false)
+    }
+
+    fn refresh_results(&mut self, prune: bool) -> BetrayalResult<usize> {
+        if self.mappings.is_empty() {
+            self.update_mappings()?;
+        }
+        let mut results = match self.region_checksums.take() {
+            Some(mut checksums) => {
+                let results = self.reread_changed_chunks(&mut checksums);
+                self.region_checksums = Some(checksums);
+                results
+            }
+            None => self.reread_results(),
+        };
+        let unreadable = self
+            .results
+            .iter()
+            .filter(|(address, _)| !results.contains_key(address))
+            .map(|(address, result)| (*address, *result))
+            .collect::<Vec<_>>();
+        self.unreadable = if prune {
+            BTreeSet::new()
+        } else {
+            results.extend(unreadable.iter().copied());
+            unreadable
+                .iter()
+                .map(|(address, _result)| *address)
+                .collect()
+        };
+        self.previous_results = std::mem::replace(&mut self.results, results);
+
+        Ok(unreadable.len())
+    }
+
+    /// every result read again, the ones that can't be read are left out
+    fn reread_results(&self) -> CurrentQueryResults<T> {
+        let size = std::mem::size_of::<T>();
+        let mut results = CurrentQueryResults::new();
+        // addresses outside of every mapping are unreadable, same as failed reads
+        for (info, map) in self.mappings.iter() {
+            let addresses = self
+                .results
+                .range(map.base..map.ceiling)
+                .map(|(address, _)| *address)
+                .collect::<Vec<_>>();
+            let values = read_memory_bulk(self.pid, &addresses, size);
+            for (address, value) in addresses.into_iter().zip(values) {
+                if let Ok(value) =
+                    value.and_then(|v| T::read_value(v).map_err(|_e| BetrayalError::PartialRead))
+                {
+                    results.insert(address, (*info, address, value));
+                }
+            }
+        }
+        results
+    }
+
+    /// what `reread_results` would return, except that the results of a chunk (see `read_chunks`)
+    /// whose checksum didn't change are kept as they are: every chunk holding results is read
+    /// in one go, but only the changed ones are decoded
+    fn reread_changed_chunks(
+        &self,
+        checksums: &mut BTreeMap<usize, u64>,
+    ) -> CurrentQueryResults<T> {
+        let size = std::mem::size_of::<T>();
+        let mut results = CurrentQueryResults::new();
+        for (info, map) in self.mappings.iter().unique_by(|(_info, map)| map.base) {
+            // the same chunks `perform_unknown_query` checksummed, but only the ones with results
+            for (start, len) in scan_chunks(map.base, map.ceiling, SCAN_CHUNK_SIZE, size - 1) {
+                let chunk_end = (start + SCAN_CHUNK_SIZE).min(map.ceiling);
+                let in_chunk = self.results.range(start..chunk_end);
+                if in_chunk.clone().next().is_none() {
+                    continue;
+                }
+                let memory = match read_memory(self.pid, start, len) {
+                    Ok(memory) => memory,
+                    Err(_e) => {
+                        checksums.remove(&start);
+                        continue;
+                    }
+                };
+                let checksum = chunk_checksum(&memory);
+                if checksums.get(&start) == Some(&checksum) {
+                    results.extend(in_chunk.map(|(address, result)| (*address, *result)));
+                    continue;
+                }
+                for (address, _result) in in_chunk {
+                    let offset = address - start;
+                    if let Some(Ok(value)) = memory
+                        .get(offset..offset + size)
+                        .map(|bytes| T::read_value(bytes.to_vec()))
+                    {
+                        results.insert(*address, (*info, *address, value));
+                    }
+                }
+                checksums.insert(start, checksum);
+            }
+        }
+        results
+    }
+
+    /// adds every address in `addresses` to the results with a placeholder value,
+    /// `update_results` reads the real ones
+    pub fn insert_results(&mut self, info: AddressInfo, addresses: std::ops::Range<usize>) {
+        // the chunks these land in have to be decoded again on the next refresh
+        if let Some(checksums) = self.region_checksums.as_mut() {
+            checksums.retain(|start, _checksum| {
+                addresses.end <= *start || start + SCAN_CHUNK_SIZE <= addresses.start
+            });
+        }
+        for address in addresses {
+            self.results
+                .insert(address, (info, address, Default::default()));
+        }
+    }
+
+    pub fn perform_write(&mut self, writer: Writer<T>) -> BetrayalResult<()> {
+        let (index, value) = writer;
+        // indices follow the displayed order, see `sort`
+        let address = self
+            .address_at_index(index)
+            .map_err(|_e| BetrayalError::BadWrite("no such address".to_string()))?;
+        self.perform_write_address(address, value)
+    }
+
+    pub fn perform_write_address(&mut self, address: usize, value: T) -> BetrayalResult<()> {
+        Self::write_at(self.pid, address, value)?;
+        self.update_results()?;
+        Ok(())
+    }
+
+    /// writes `value` to every result, refreshing them only once at the end
+    pub fn perform_write_all(&mut self, value: T) -> BetrayalResult<usize> {
+        let addresses = self.results.keys().copied().collect::<Vec<_>>();
+        for address in &addresses {
+            Self::write_at(self.pid, *address, value)?;
+        }
+        self.update_results()?;
+        Ok(addresses.len())
+    }
+
+    /// writes to an address that doesn't have to be in the results, as long as it's writable
+    pub fn perform_write_literal(&mut self, address: usize, value: T) -> BetrayalResult<()> {
+        self.update_mappings()?;
+        match AddressInfo::from_address(self, self.pid, address) {
+            Ok(info) if info.writable => self.perform_write_address(address, value),
+            Ok(_) => Err(BetrayalError::BadWrite(format!(
+                "0x{:x} is not writable",
+                address
+            ))),
+            Err(_) => Err(BetrayalError::BadWrite(format!(
+                "0x{:x} is not mapped",
+                address
+            ))),
+        }
+    }
+
+    /// writes raw bytes instead of a `T`, the whole range has to be inside one writable mapping
+    pub fn perform_write_bytes(&mut self, address: usize, bytes: Vec<u8>) -> BetrayalResult<()> {
+        self.update_mappings()?;
+        let end = address.checked_add(bytes.len()).ok_or_else(|| {
+            BetrayalError::BadWrite(format!(
+                "0x{:x} + {} bytes runs past the end of the address space",
+                address,
+                bytes.len()
+            ))
+        })?;
+        let writable = self
+            .mappings()?
+            .find(|(_info, map)| map.base <= address && end <= map.ceiling)
+            .map(|(info, _map)| info.writable);
+        match writable {
+            Some(true) => {
+                write_memory(self.pid, address, bytes)?;
+                self.update_results()
+            }
+            Some(false) => Err(BetrayalError::BadWrite(format!(
+                "0x{:x} is not writable",
+                address
+            ))),
+            None => Err(BetrayalError::BadWrite(format!(
+                "0x{:x} - 0x{:x} is not inside a single mapping",
+                address, end
+            ))),
+        }
+    }
+
+    pub fn perform_new_query(&mut self, filter: Filter<T>) -> BetrayalResult<()> {
+        let results = self
+            .query(filter.clone())?
+            .into_par_iter()
+            .filter(|v| filter.clone().matches(*v, &self.results))
+            .map(|(info, address, value)| (address, (info, address, value)))
+            .collect();
+        self.results = results;
+        self.unreadable.clear();
+        // the fresh values are newer than the checksums
+        self.region_checksums = None;
+        self.changed_in = None;
+        Ok(())
+    }
+
+    /// replaces the results with every address currently holding the same value
+    /// as the result at `index`, eg. cached copies of a stat
+    pub fn perform_copies_query(&mut self, index: usize) -> BetrayalResult<T> {
+        let address = self.address_at_index(index)?;
+        let (_info, _address, value) = self.read_at(self.pid, address)?;
+        self.push_history();
+        if let Err(e) = self.perform_new_query(Filter::IsEqual(value)) {
+            // same as `perform_query`, a failed scan leaves nothing to undo
+            self.history.pop();
+            return Err(e);
+        }
+        Ok(value)
+    }
+
+    /// amount of values an unknown initial value scan would snapshot
+    pub fn unknown_candidate_count(&mut self) -> BetrayalResult<usize> {
+        self.update_mappings()?;
+        Ok(self
+            .mappings()?
+            .into_iter()
+            .filter(|(info, map)| info.writable && self.in_scanned_region(map))
+            .map(|(_info, map)| (map.ceiling - map.base) / self.alignment.step::<T>())
+            .sum())
+    }
+
+    /// snapshots every value in writable regions (respecting the alignment), so that the following
+    /// filters (inc / dec / unch / c) have something to compare against
+    pub fn perform_unknown_query(&mut self) -> BetrayalResult<()> {
+        self.update_mappings()?;
+        let pid = self.pid;
+        let step = self.alignment.step::<T>();
+        let mappings: Vec<_> = self
+            .mappings()?
+            .into_iter()
+            .filter(|(info, map)| info.writable && self.in_scanned_region(map))
+            .unique_by(|(_info, m)| m.base)
+            .collect();
+
+        let results: Arc<Mutex<Vec<AddressValue<T>>>> = Default::default();
+        let checksums: Mutex<BTreeMap<usize, u64>> = Default::default();
+        let changed_regions = self.changed_regions;
+        let progress = ScanProgress::new(mappings.len(), self.quiet);
+        mappings.into_par_iter().for_each(|(info, map)| {
+            let results = Arc::clone(&results);
+            // values straddling a chunk boundary are read again by the next chunk
+            let overlap = size_of::<T>() - 1;
+            for (start, chunk_end, memory) in read_chunks(pid, map.base, map.ceiling, overlap) {
+                if cancel::requested() {
+                    return;
+                }
+                let mut results_chunk = match memory {
+                    Ok(m) => {
+                        if changed_regions {
+                            // the very bytes the values come from, anything later could be newer
+                            checksums.lock().insert(start, chunk_checksum(&m));
+                        }
+                        T::possible_values(&m[..], start, step)
+                            .filter(|(address, _value)| *address < chunk_end)
+                            .map(|(address, value)| (info.clone(), address, value))
+                            .collect()
+                    }
+                    Err(e) => {
+                        // whatever made this chunk unreadable most likely covers the rest too
+                        progress.region_skipped(map.ceiling - start, &e);
+                        break;
+                    }
+                };
+                results.lock().append(&mut results_chunk);
+            }
+            progress.region_done();
+        });
+
+        cancel::check()?;
+        progress.check_permission()?;
+        println!(" :: snapshot done ::");
+        progress.print_skipped();
+        self.push_history();
+        self.results = results
+            .lock()
+            .drain(..)
+            .map(|(info, address, value)| (address, (info, address, value)))
+            .collect();
+        self.unreadable.clear();
+        self.region_checksums = if changed_regions {
+            Some(checksums.into_inner())
+        } else {
+            None
+        };
+        self.changed_in = None;
+        Ok(())
+    }
+
+    /// "f changed-in <region>": the first use (for that region) snapshots only the maps whose path
+    /// contains `region`, every following one keeps the results in those maps that changed since,
+    /// returns whether it was the snapshot
+    pub fn perform_changed_in(&mut self, region: &str) -> BetrayalResult<bool> {
+        self.update_mappings()?;
+        let ranges = self
+            .mappings
+            .iter()
+            .filter(|(_info, map)| {
+                matches!(&map.pathname, procmaps::Path::MappedFile(path) if path.contains(region))
+            })
+            .map(|(_info, map)| (map.base, map.ceiling))
+            .collect::<Vec<_>>();
+        if ranges.is_empty() {
+            return Err(BetrayalError::BadCommand(format!(
+                "no map matches {:?}, see \"maps\"",
+                region
+            )));
+        }
+        let in_region = |address: &usize| {
+            ranges
+                .iter()
+                .any(|(base, ceiling)| base <= address && address < ceiling)
+        };
+        if self.changed_in.as_deref() != Some(region) {
+            let scanned_region = self.region.replace(region.to_string());
+            let snapshot = self.perform_unknown_query();
+            self.region = scanned_region;
+            snapshot?;
+            self.changed_in = Some(region.to_string());
+            return Ok(true);
+        }
+        if self.results.is_empty() {
+            // filtering nothing would start a fresh scan of everything
+            return Ok(false);
+        }
+        self.perform_query(Filter::Changed)?;
+        self.results.retain(|address, _result| in_region(address));
+        Ok(false)
+    }
+
+    /// translates the index shown next to a result into its address
+    pub fn address_at_index(&self, index: usize) -> BetrayalResult<usize> {
+        self.displayed_results()
+            .get(index)
+            .map(|(_info, address, _value)| *address)
+            .ok_or_else(|| BetrayalError::BadCommand(format!("no result at index {}", index)))
+    }
+
+    /// results in the order they are displayed, see `sort`
+    pub fn displayed_results(&self) -> Vec<&AddressValue<T>> {
+        let mut results = self.results.values().collect::<Vec<_>>();
+        let by_value = |(_, _, a): &&AddressValue<T>, (_, _, b): &&AddressValue<T>| {
+            a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+        };
+        match self.sort {
+            SortOrder::Address => {}
+            SortOrder::Value => results.sort_by(by_value),
+            SortOrder::ValueDescending => results.sort_by(|a, b| by_value(b, a)),
+        }
+        results
+    }
+
+    /// `index,address_dec,address_hex,value,writable,static_location` rows with a header, in display order,
+    /// followed by the static location split into `static_map_path,static_base,static_offset`
+    pub fn results_csv(&self) -> String {
+        std::iter::once(
+            "index,address_dec,address_hex,value,writable,static_location,static_map_path,static_base,static_offset"
+                .to_string(),
+        )
+        .chain(self.displayed_results().into_iter().enumerate().map(
+            |(index, (info, address, value))| {
+                let location = info.static_location(self.pid, *address);
+                format!(
+                    "{},{},0x{:x},{},{},{}",
+                    index,
+                    address,
+                    address,
+                    value,
+                    info.writable,
+                    match location {
+                        Some(location) => format!(
+                            "{},{},{},{}",
+                            csv_field(&location.to_string()),
+                            csv_field(&location.map_path),
+                            location.base,
+                            location.offset
+                        ),
+                        None => String::from(",,,"),
+                    }
+                )
+            },
+        ))
+            .map(|row| row + "\n")
+            .collect()
+    }
+
+    pub fn delete_at_index(&mut self, index: usize) -> BetrayalResult<AddressValue<T>> {
+        let address = self.address_at_index(index)?;
+        self.results
+            .remove(&address)
+            .ok_or_else(|| BetrayalError::BadCommand(format!("no result at index {}", index)))
+    }
+
+    pub fn label_at_index(&mut self, index: usize, label: String) -> BetrayalResult<usize> {
+        let address = self.address_at_index(index)?;
+        self.labels.insert(address, label);
+        Ok(address)
+    }
+
+    pub fn format_value(&self, value: &T) -> String {
+        if self.hex_display {
+            value.display_hex()
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// `(+15)`, `(-3)` or `(=)` compared to the value before the last refresh
+    pub fn format_delta(&self, address: usize, value: &T) -> String {
+        match self.previous_results.get(&address) {
+            Some((_info, _address, previous)) => format_difference(*previous, *value)
+                .map(|difference| format!("({})", difference))
+                .unwrap_or_default(),
+            None => String::new(),
+        }
+    }
+
+    /// reads the results at both indices (fresh, not the values from the last refresh)
+    pub fn compare_at_indices(
+        &mut self,
+        a: usize,
+        b: usize,
+    ) -> BetrayalResult<(AddressValue<T>, AddressValue<T>)> {
+        let (a, b) = (self.address_at_index(a)?, self.address_at_index(b)?);
+        Ok((self.read_at(self.pid, a)?, self.read_at(self.pid, b)?))
+    }
+
+    fn push_history(&mut self) {
+        if self.history.len() >= MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(self.results.clone());
+    }
+
+    /// restores results from before the last scan, returns how many snapshots are left
+    pub fn undo(&mut self) -> BetrayalResult<usize> {
+        self.results = self
+            .history
+            .pop()
+            .ok_or_else(|| BetrayalError::BadCommand("nothing to undo".to_string()))?;
+        self.unreadable.clear();
+        // the restored values are older than the checksums
+        self.region_checksums = None;
+        // and might be from before the snapshot
+        self.changed_in = None;
+        Ok(self.history.len())
+    }
+
+    /// replaces the results with just `address` (if it can be read), returns its value
+    pub fn only(&mut self, address: usize) -> BetrayalResult<T> {
+        self.update_mappings()?;
+        let result = self.read_at(self.pid, address)?;
+        self.push_history();
+        self.results = vec![(address, result)].into_iter().collect();
+        self.unreadable.clear();
+        self.region_checksums = None;
+        self.changed_in = None;
+        self.page = 0;
+        Ok(result.2)
+    }
+
+    /// forgets the results (along with their history and labels) so the next filter is a fresh scan,
+    /// returns how many results were dropped
+    pub fn clear(&mut self) -> usize {
+        let dropped = self.results.len();
+        self.results.clear();
+        self.unreadable.clear();
+        self.history.clear();
+        self.labels.clear();
+        self.region_checksums = None;
+        self.changed_in = None;
+        self.page = 0;
+        dropped
+    }
+
+    /// collapses the overlapping matches of a byte-granular scan: drops every address that isn't
+    /// aligned to the size of `T` when an aligned result overlapping it holds the same value,
+    /// returns how many were dropped
+    pub fn dedupe(&mut self) -> usize {
+        let size = std::mem::size_of::<T>();
+        let duplicates = self
+            .results
+            .iter()
+            .filter(|(address, _)| *address % size != 0)
+            .filter(|(address, (_, _, value))| {
+                let aligned_below = *address - *address % size;
+                vec![aligned_below, aligned_below + size]
+                    .into_iter()
+                    .any(|aligned| {
+                        matches!(self.results.get(&aligned), Some((_, _, other)) if other == value)
+                    })
+            })
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        if !duplicates.is_empty() {
+            self.push_history();
+            for address in duplicates.iter() {
+                self.results.remove(address);
+            }
+        }
+        duplicates.len()
+    }
+
+    pub fn perform_query(&mut self, filter: Filter<T>) -> BetrayalResult<()> {
+        self.push_history();
+        let filtered = self.filter_results(filter);
+        if filtered.is_err() {
+            // results are only replaced on success (eg. not after Ctrl-C), there's nothing to undo
+            self.history.pop();
+        }
+        filtered
+    }
+
+    fn filter_results(&mut self, filter: Filter<T>) -> BetrayalResult<()> {
+        if let Filter::BytePattern(_) | Filter::Triplet(..) = filter {
+            // patterns can only be matched against raw memory, so it's always a fresh scan
+            return self.perform_new_query(filter);
+        }
+        if self.results.is_empty() {
+            self.perform_new_query(filter.clone())?;
+        }
+        let current_results = self.results.clone();
+        // a value that can't be read can't match anything either
+        self.update_results_pruning()?;
+        self.results
+            .retain(|_k, v| filter.clone().matches(*v, &current_results));
+
+        Ok(())
+    }
+
+    pub fn mappings_all_with_unreadable(pid: i32) -> BetrayalResult<Vec<(AddressInfo, Map)>> {
+        let mappings = std::mem::take(
+            procmaps::Mappings::from_pid(pid)
+                .map_err(|_e| {
+                    if process::has_exited(pid) {
+                        BetrayalError::ProcessGone
+                    } else {
+                        BetrayalError::BadPid
+                    }
+                })?
+                .deref_mut(),
+        );
+        Ok(mappings
+            .into_iter()
+            .map(|m| {
+                (
+                    AddressInfo {
+                        writable: m.perms.writable,
+                    },
+                    m,
+                )
+            })
+            .collect())
+    }
+
+    pub fn mappings_all(pid: i32) -> BetrayalResult<Vec<(AddressInfo, Map)>> {
+        Ok(Self::mappings_all_with_unreadable(pid)?
+            .into_iter()
+            .collect())
+    }
+
+    fn mappings(&self) -> BetrayalResult<Box<impl Iterator<Item = &(AddressInfo, Map)>>> {
+        Ok(Box::new(self.mappings.iter()))
+    }
+
+    fn in_scanned_region(&self, map: &Map) -> bool {
+        match (&self.region, &map.pathname) {
+            (None, _) => true,
+            (Some(region), procmaps::Path::MappedFile(path)) => path.contains(region.as_str()),
+            (Some(_), _) => false,
+        }
+    }
+
+    pub fn in_address_space(&self, address: usize) -> BetrayalResult<bool> {
+        Ok(self
+            .mappings()?
+            .into_iter()
+            .any(|(_info, map)| map.base <= address && address < map.ceiling))
+    }
+
+    pub fn update_mappings(&mut self) -> BetrayalResult<()> {
+        self.mappings = Self::mappings_all(self.pid)?;
+        Ok(())
+    }
+    fn query<'process, 'result>(
+        &'process mut self,
+        filter: Filter<T>,
+        // ) -> BetrayalResult<Box<impl Iterator<Item = QueryResult> + 'result>>
+    ) -> BetrayalResult<Vec<AddressValue<T>>>
+    where
+        'process: 'result,
+    {
+        self.update_mappings()?;
+
+        let pid = self.pid;
+        let step = self.alignment.step::<T>();
+        let mappings = self.mappings()?;
+        let mappings: Vec<_> = mappings
+            .into_iter()
+            .filter(|(info, map)| {
+                (info.writable || self.scan_readonly) && self.in_scanned_region(map)
+            })
+            .unique_by(|(_info, m)| m.base)
+            .unique_by(|(_info, m)| m.ceiling)
+            .collect();
+
+        let started = std::time::Instant::now();
+        let bytes_scanned = AtomicUsize::new(0);
+        let results: Arc<Mutex<Vec<AddressValue<T>>>> = Default::default();
+        let too_many_results = AtomicBool::new(false);
+        let max_results = self.max_results;
+        let progress = ScanProgress::new(mappings.len(), self.quiet);
+        // values (and patterns) straddling a chunk boundary are read again by the next chunk
+        let overlap = match &filter {
+            Filter::BytePattern(pattern) => pattern.len().max(size_of::<T>()),
+            Filter::Triplet(..) => size_of::<T>() * 3,
+            _ => size_of::<T>(),
+        } - 1;
+        mappings.into_par_iter().for_each(|(info, map)| {
+            let results = Arc::clone(&results);
+            let filter = filter.clone();
+            let dummy_results = Default::default(); // this should work for now cause this is only ran on the initial scan... I hope
+            for (start, chunk_end, memory) in read_chunks(pid, map.base, map.ceiling, overlap) {
+                if cancel::requested() || too_many_results.load(Ordering::Relaxed) {
+                    return;
+                }
+                // matches starting in the overlap belong to the next chunk
+                let in_chunk = |(address, _value): &(usize, T)| *address < chunk_end;
+                let mut results_chunk = match memory {
+                    Ok(m) => {
+                        bytes_scanned.fetch_add(chunk_end - start, Ordering::Relaxed);
+                        match &filter {
+                            Filter::BytePattern(pattern) => {
+                                memory::find_byte_pattern::<T>(&m, start, pattern)
+                                    .into_iter()
+                                    .filter(in_chunk)
+                                    .map(|(address, value)| (info.clone(), address, value))
+                                    .collect()
+                            }
+                            Filter::Triplet(targets, epsilon) => {
+                                memory::find_triplets(&m, start, step, targets, *epsilon)
+                                    .into_iter()
+                                    .filter(in_chunk)
+                                    .map(|(address, value)| (info.clone(), address, value))
+                                    .collect()
+                            }
+                            _ => T::possible_values(&m[..], start, step)
+                                .filter(in_chunk)
+                                .map(|(address, value)| (info.clone(), address, value))
+                                .filter(|result| filter.clone().matches(*result, &dummy_results))
+                                // one region alone can already be too much
+                                .take(max_results.map(|max| max + 1).unwrap_or(usize::MAX))
+                                .collect(),
+                        }
+                    }
+                    Err(e) => {
+                        // whatever made this chunk unreadable most likely covers the rest too
+                        progress.region_skipped(map.ceiling - start, &e);
+                        break;
+                    }
+                };
+                let mut results = results.lock();
+                results.append(&mut results_chunk);
+                if max_results.map(|max| results.len() > max).unwrap_or(false) {
+                    too_many_results.store(true, Ordering::Relaxed);
+                    results.clear();
+                }
+            }
+            progress.region_done();
+        });
+
+        // a partial scan would look like a complete one, so nothing of it is kept
+        cancel::check()?;
+        progress.check_permission()?;
+        if let (true, Some(max)) = (too_many_results.into_inner(), max_results) {
+            return Err(BetrayalError::TooManyResults(max));
+        }
+        let results = results.lock().clone();
+        println!(
+            " :: scanning done :: scanned {} in {:.1}s, {} matches",
+            helpers::human_bytes(bytes_scanned.into_inner()),
+            started.elapsed().as_secs_f64(),
+            results.len()
+        );
+        progress.print_skipped();
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod test_process_query {
+    use super::*;
+
+    /// a query on this very process, holding `results` as if a scan had found them there
+    fn own_process(results: &[(usize, u32)]) -> ProcessQuery<u32> {
+        let mut process = ProcessQuery::<u32>::new(std::process::id() as i32);
+        process.quiet = true;
+        for (address, value) in results {
+            process
+                .results
+                .insert(*address, (AddressInfo { writable: true }, *address, *value));
+        }
+        process
+    }
+
+    /// file name of the test binary, its writable maps are small enough to snapshot whole
+    fn own_executable() -> String {
+        let executable = std::env::current_exe().unwrap();
+        executable
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_scan_matching_too_much_is_aborted() {
+        let mut process = own_process(&[]);
+        process.max_results = Some(0);
+        assert!(matches!(
+            process.perform_query(Filter::InRange((u32::MIN, u32::MAX))),
+            Err(BetrayalError::TooManyResults(0))
+        ));
+        assert!(process.results.is_empty());
+        assert!(process.history.is_empty());
+    }
+
+    #[test]
+    fn test_failed_copies_scan_leaves_nothing_to_undo() {
+        let value = Box::new(1337u32);
+        let address = &*value as *const u32 as usize;
+        let mut process = own_process(&[(address, 1337)]);
+        // the value itself is always found, so even that is too much
+        process.max_results = Some(0);
+        assert!(matches!(
+            process.perform_copies_query(0),
+            Err(BetrayalError::TooManyResults(0))
+        ));
+        assert!(process.results.contains_key(&address));
+        assert!(process.history.is_empty());
+    }
+
+    #[test]
+    fn test_only_plain_refresh_keeps_unreadable_addresses() {
+        let value = Box::new(1337u32);
+        let readable = &*value as *const u32 as usize;
+        let mut process = own_process(&[(readable, 7), (0x8, 7)]);
+        assert_eq!(process.update_results_keeping_unreadable().unwrap(), 1);
+        assert_eq!(process.results[&readable].2, 1337);
+        assert_eq!(process.results[&0x8].2, 7);
+        assert_eq!(
+            process.unreadable.iter().copied().collect::<Vec<_>>(),
+            vec![0x8]
+        );
+        process.update_results().unwrap();
+        assert_eq!(
+            process.results.keys().copied().collect::<Vec<_>>(),
+            vec![readable]
+        );
+        assert!(process.unreadable.is_empty());
+    }
+
+    #[test]
+    fn test_changed_regions_give_the_same_results_as_rereading_everything() {
+        static mut VALUES: [u32; 4] = [1, 2, 3, 4];
+        let ours = unsafe { VALUES.as_ptr() as usize..VALUES.as_ptr().add(VALUES.len()) as usize };
+        let mut processes = vec![false, true]
+            .into_iter()
+            .map(|changed_regions| {
+                let mut process = own_process(&[]);
+                process.changed_regions = changed_regions;
+                process.region = Some(own_executable());
+                process.perform_unknown_query().unwrap();
+                process
+            })
+            .collect::<Vec<_>>();
+        // other tests keep changing the rest of the binary's memory
+        let found = |process: &ProcessQuery<u32>| {
+            process
+                .results
+                .range(ours.clone())
+                .map(|(address, result)| (*address, result.2))
+                .collect::<Vec<_>>()
+        };
+
+        // a plain refresh in between has to move the checksums along with the values,
+        // otherwise going back to the snapshotted value looks unchanged
+        unsafe { std::ptr::write_volatile(&mut VALUES[1], 7) };
+        for process in processes.iter_mut() {
+            process.update_results_keeping_unreadable().unwrap();
+        }
+        unsafe { std::ptr::write_volatile(&mut VALUES[1], 2) };
+        for filter in vec![Filter::Changed, Filter::Unchanged] {
+            for process in processes.iter_mut() {
+                process.perform_query(filter.clone()).unwrap();
+            }
+            assert_eq!(found(&processes[0]), found(&processes[1]));
+        }
+        assert_eq!(found(&processes[1]), vec![(ours.start + 4, 2)]);
+    }
+
+    #[test]
+    fn test_changed_in_snapshots_then_narrows_within_region() {
+        let executable = own_executable();
+        let mut process = own_process(&[]);
+        assert!(process
+            .perform_changed_in("no such file, hopefully")
+            .is_err());
+        assert!(process.perform_changed_in(&executable).unwrap());
+        assert!(!process.results.is_empty());
+        assert!(process.region.is_none());
+        let in_region = process.results.keys().copied().collect::<Vec<_>>();
+        assert!(!process.perform_changed_in(&executable).unwrap());
+        assert!(process
+            .results
+            .keys()
+            .all(|address| in_region.contains(address)));
+
+        // nothing left is still the same snapshot, not a reason to take a new one
+        process.results.retain(|_address, _result| false);
+        assert!(!process.perform_changed_in(&executable).unwrap());
+        assert!(process.results.is_empty());
+        process.clear();
+        assert!(process.perform_changed_in(&executable).unwrap());
+    }
+
+    #[test]
+    fn test_only_replaces_results() {
+        let value = Box::new(2137u32);
+        let address = &*value as *const u32 as usize;
+        let mut process = own_process(&[(0x8, 7)]);
+        assert_eq!(process.only(address).unwrap(), 2137);
+        assert_eq!(
+            process.results.keys().copied().collect::<Vec<_>>(),
+            vec![address]
+        );
+        assert!(process.only(0x8).is_err());
+        process.undo().unwrap();
+        assert_eq!(
+            process.results.keys().copied().collect::<Vec<_>>(),
+            vec![0x8]
+        );
+    }
+
+    #[test]
+    fn test_dedupe_collapses_unaligned_duplicates_to_aligned_address() {
+        // 0x1001..0x1003 overlap the aligned 0x1000, 0x1005 holds a different value than 0x1004
+        // and 0x1009 has no aligned neighbour at all
+        let mut process = own_process(&[
+            (0x1000, 7),
+            (0x1001, 7),
+            (0x1003, 7),
+            (0x1004, 1),
+            (0x1005, 2),
+            (0x1009, 7),
+        ]);
+        assert_eq!(process.dedupe(), 2);
+        assert_eq!(
+            process.results.keys().copied().collect::<Vec<_>>(),
+            vec![0x1000, 0x1004, 0x1005, 0x1009]
+        );
+        assert_eq!(process.dedupe(), 0);
+        process.undo().unwrap();
+        assert_eq!(process.results.len(), 6);
+    }
+}
+
+/// prints how many of the scanned regions are done, every 10%
+struct ScanProgress {
+    done: AtomicUsize,
+    total: usize,
+    quiet: bool,
+    /// regions that failed to read and their total size
+    skipped: AtomicUsize,
+    skipped_bytes: AtomicUsize,
+    /// a region was unreadable because of missing ptrace permissions, so all of them are
+    denied: AtomicBool,
+}
+
+impl ScanProgress {
+    fn new(total: usize, quiet: bool) -> Self {
+        Self {
+            done: AtomicUsize::new(0),
+            total,
+            quiet,
+            skipped: AtomicUsize::new(0),
+            skipped_bytes: AtomicUsize::new(0),
+            denied: AtomicBool::new(false),
+        }
+    }
+
+    fn region_skipped(&self, bytes: usize, error: &BetrayalError) {
+        self.skipped.fetch_add(1, Ordering::Relaxed);
+        self.skipped_bytes.fetch_add(bytes, Ordering::Relaxed);
+        if let BetrayalError::PermissionDenied = error {
+            self.denied.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// an empty scan result would only hide the actual problem
+    fn check_permission(&self) -> BetrayalResult<()> {
+        if self.denied.load(Ordering::Relaxed) {
+            Err(BetrayalError::PermissionDenied)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// a value living in one of those regions can't be found, so this is printed even when quiet
+    fn print_skipped(&self) {
+        let skipped = self.skipped.load(Ordering::Relaxed);
+        if skipped == 0 {
+            return;
+        }
+        println!(
+            " :: skipped {} unreadable regions, {}",
+            skipped,
+            helpers::human_bytes(self.skipped_bytes.load(Ordering::Relaxed))
+        );
+    }
+
+    fn region_done(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.quiet || done * 10 / self.total == (done - 1) * 10 / self.total {
+            return;
+        }
+        println!(
+            " :: scanning {}% ({}/{} regions)",
+            done * 100 / self.total,
+            done,
+            self.total
+        );
+    }
+}
+
+/// quotes the field if it contains anything that would break the row
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| matches!(c, ',' | '"' | '\n')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test_csv {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("2137"), "2137");
+        assert_eq!(
+            csv_field("static_address(PID,\"game\")+16"),
+            "\"static_address(PID,\"\"game\"\")+16\""
+        );
+    }
+}
+
+/// how often frozen values are written
+const FREEZE_INTERVAL_MS: u64 = 50;
+/// failing freezes double their interval this many times at most (50ms -> 1.6s)
+const FREEZE_MAX_BACKOFF: usize = 5;
+
+pub fn freeze<T: 'static + ReadFromBytes>(
+    process: &Arc<Mutex<ProcessQuery<T>>>,
+    freezes: &FreezeRegistry<T>,
+    address: usize,
+    value: T,
+) {
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let process = Arc::clone(process);
+        let stop = Arc::clone(&stop);
+        std::thread::spawn(move || {
+            let mut failures = 0;
+            while !stop.load(Ordering::Relaxed) {
+                let mut process = process.lock();
+                match process.perform_write_address(address, value) {
+                    Ok(_) => failures = 0,
+                    Err(e) if failures < process.freeze_retries => {
+                        failures += 1;
+                        eprintln!(
+                            " :: [WARN] :: writing to 0x{:x} failed with {} ({}/{}), retrying",
+                            address, e, failures, process.freeze_retries
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!(" :: [ERR] :: Writer thread crashed with {}. Aborting.", e);
+                        break;
+                    }
+                };
+                drop(process);
+
+                // backs off while the address is unavailable, eg. during a level load
+                let delay = FREEZE_INTERVAL_MS << failures.min(FREEZE_MAX_BACKOFF);
+                std::thread::sleep(std::time::Duration::from_millis(delay));
+            }
+        })
+    };
+    let previous = freezes.lock().insert(
+        address,
+        FreezeHandle {
+            value,
+            stop,
+            thread,
+        },
+    );
+    if let Some(previous) = previous {
+        previous.stop();
+    }
+}
+
+/// what `--variable_type` and "reinterpret" accept
+pub const VARIABLE_TYPES: &[&str] = &[
+    "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "f32", "f64",
+];
+
+/// `+15`, `-3` or `=` going from `from` to `to`, `None` when they can't be compared (NaN)
+pub fn format_difference<T: ReadFromBytes>(from: T, to: T) -> Option<String> {
+    use std::cmp::Ordering::*;
+    let (sign, smaller, bigger) = match to.partial_cmp(&from)? {
+        Greater => ("+", from, to),
+        Less => ("-", to, from),
+        Equal => return Some("=".to_string()),
+    };
+    let magnitude = if T::IS_FLOAT {
+        // at worst it's inf, floats don't overflow
+        (bigger - smaller).to_string()
+    } else {
+        // the distance between two i32s doesn't always fit an i32 (nor an u64 between two i64s)
+        (bigger.to_i128() - smaller.to_i128()).to_string()
+    };
+    Some(format!("{}{}", sign, magnitude))
+}
+
+#[cfg(test)]
+mod test_format_difference {
+    use super::*;
+
+    #[test]
+    fn test_unsigned_difference_does_not_underflow() {
+        assert_eq!(format_difference(10u8, 3), Some("-7".to_string()));
+        assert_eq!(format_difference(3u8, 10), Some("+7".to_string()));
+        assert_eq!(format_difference(5i32, 5), Some("=".to_string()));
+        assert_eq!(format_difference(f32::NAN, 1.0), None);
+    }
+
+    #[test]
+    fn test_difference_at_the_extremes() {
+        assert_eq!(
+            format_difference(-2_000_000_000i32, 2_000_000_000),
+            Some("+4000000000".to_string())
+        );
+        assert_eq!(
+            format_difference(i64::MAX, i64::MIN),
+            Some("-18446744073709551615".to_string())
+        );
+        assert_eq!(
+            format_difference(i8::MIN, i8::MAX),
+            Some("+255".to_string())
+        );
+        assert_eq!(format_difference(u8::MAX, 0), Some("-255".to_string()));
+        assert_eq!(
+            format_difference(0u64, u64::MAX),
+            Some(format!("+{}", u64::MAX))
+        );
+        assert_eq!(
+            format_difference(f32::MIN, f32::MAX),
+            Some("+inf".to_string())
+        );
+    }
+}
@@ -1,55 +1,42 @@
-#![feature(box_syntax)]
-
-pub mod commands;
-pub mod helpers;
-pub mod memory;
-pub mod neighbour_values;
-pub mod reclass;
-use crate::memory::ReadFromBytes;
-
+use betrayal_engine::{
+    cancel,
+    commands::{Command, HELP_TEXT},
+    error::BetrayalError,
+    find_near_pointers, format_difference, freeze, helpers, log_graph, map_name,
+    memory::{self, Alignment, ReadFromBytes},
+    pointer_map, process, read_memory, reclass, resolve_pointer_chain, server, snapshot,
+    AddressInfo, FreezeRegistry, PointerMap, ProcessQuery,
+};
 use clap::{crate_version, App, Arg, Subcommand};
-use commands::{Command, HELP_TEXT};
 use itertools::Itertools;
-use neighbour_values::NeighbourValuesQuery;
+use owo_colors::OwoColorize;
 use parking_lot::Mutex;
-use petgraph::data::Build;
-use petgraph::dot::Dot;
-use petgraph::graph::NodeIndex;
-use petgraph::visit::{Dfs, EdgeIndexable};
-use rayon::prelude::*;
-use serde::{Deserialize, Serialize};
-use std::convert::{TryFrom, TryInto};
-use std::ops::Index;
+use rustyline::{error::ReadlineError, Editor};
+use std::convert::TryFrom;
+use std::io::{self, BufRead, Write};
 use std::path::PathBuf;
-use std::thread::JoinHandle;
-use std::{collections::BTreeMap, fs::File, io::Write, path::Path, str::FromStr, sync::Arc};
-use std::{
-    io::{self, BufRead},
-    ops::DerefMut,
-};
-
-use nix::{
-    sys::uio::{process_vm_readv, process_vm_writev, IoVec, RemoteIoVec},
-    unistd::Pid,
-};
-
-use error::{BetrayalError, BetrayalResult};
-use procmaps::{self, Map};
-
-use crate::neighbour_values::NeighbourValues;
-mod error;
-mod process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{fs::File, path::Path, str::FromStr, sync::Arc};
 
 pub fn take_input<T: FromStr>(prompt: &str) -> Result<T, <T as FromStr>::Err> {
     let mut input_string = String::new();
-    print!("\n{} >> ", prompt);
-    std::io::stdout().flush();
+    {
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        write!(stdout, "\n{} >> ", prompt).expect("Failed to write prompt");
+        stdout.flush().expect("Failed to flush stdout");
+    }
     std::io::stdin()
         .read_line(&mut input_string)
         .expect("Failed to read line");
     T::from_str(input_string.trim())
 }
 
+/// interactive command history, kept between sessions
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".betrayal_engine_history"))
+}
+
 fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 where
     P: AsRef<Path>,
@@ -57,488 +44,485 @@ where
     let file = File::open(filename)?;
     Ok(io::BufReader::new(file).lines())
 }
-
-pub fn read_memory(pid: i32, address: usize, bytes_requested: usize) -> BetrayalResult<Vec<u8>> {
-    let mut buffer = vec![0u8; bytes_requested];
-    let remote = RemoteIoVec {
-        base: address,
-        len: bytes_requested,
-    };
-    let bytes_read = match process_vm_readv(
-        Pid::from_raw(pid),
-        &[IoVec::from_mut_slice(&mut buffer)],
-        &[remote],
-    ) {
-        Ok(bytes_read) => bytes_read,
-        Err(_error) => {
-            return Err(BetrayalError::PartialRead);
-        }
-    };
-
-    if bytes_read != bytes_requested {
-        return Err(BetrayalError::PartialRead);
-    }
-    Ok(buffer)
+/// `--color`, `auto` colors the output only for terminals and respects `NO_COLOR`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
 
-pub fn write_memory(pid: i32, address: usize, buffer: Vec<u8>) -> BetrayalResult<()> {
-    let bytes_requested = buffer.len();
-    let remote = RemoteIoVec {
-        base: address,
-        len: bytes_requested,
-    };
-    match process_vm_writev(Pid::from_raw(pid), &[IoVec::from_slice(&buffer)], &[remote]) {
-        Ok(bytes_written) => {
-            if bytes_written != bytes_requested {
-                return Err(BetrayalError::BadWrite(format!(
-                    "bad write length: {} != {}",
-                    bytes_written, bytes_requested
-                )));
-            } else {
-                Ok(())
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                std::env::var_os("NO_COLOR").is_none() && nix::unistd::isatty(1).unwrap_or(false)
             }
         }
-        Err(e) => return Err(BetrayalError::BadWrite(format!("write error: {}", e))),
     }
 }
 
-pub type AddressValue<T: ReadFromBytes> = (AddressInfo, usize, T);
-
-// #[derive(Debug)]
-// pub enum AddressValueAs {
-//     I32(AddressValue<i32>),
-// }
-
-pub type CurrentQueryResults<T: ReadFromBytes> = BTreeMap<usize, AddressValue<T>>;
-
-#[derive(Debug)]
-pub struct ProcessQuery<T: ReadFromBytes> {
-    pub pid: i32,
-    pub results: CurrentQueryResults<T>,
-    pub mappings: Vec<(AddressInfo, Map)>,
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Filter<T: ReadFromBytes> {
-    IsEqual(T),
-    InRange((T, T)),
-    Any,
-    ChangedBy(T),
-    InAddressRanges(Vec<(usize, usize)>),
-}
+impl FromStr for ColorChoice {
+    type Err = BetrayalError;
 
-pub type Writer<T: ReadFromBytes> = (usize, T);
-
-impl<T: ReadFromBytes> Filter<T> {
-    pub fn matches(
-        self,
-        result: AddressValue<T>,
-        current_results: &CurrentQueryResults<T>,
-    ) -> bool {
-        let (info, address, current_value) = result;
-        match self {
-            Self::IsEqual(v) => v == current_value,
-            Self::InRange((base, ceiling)) => base <= current_value && current_value <= ceiling,
-            Self::Any => true,
-            Self::ChangedBy(diff) => current_results
-                .get(&address)
-                // .find(|(candidate_address, _value)| address == *candidate_address)
-                .map(|(_info, _a, value)| current_value + diff == *value)
-                .unwrap_or(false),
-            Self::InAddressRanges(ranges) => ranges
-                .iter()
-                .any(|(base, ceiling)| base <= &address && &address <= ceiling),
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            other => Err(BetrayalError::BadCommand(format!(
+                "color must be one of auto, always or never, got {}",
+                other
+            ))),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct AddressInfo {
-    pub writable: bool,
+/// unknown value scans bigger than this need to be confirmed by the user
+const UNKNOWN_SCAN_CONFIRMATION_THRESHOLD: usize = 10_000_000;
+/// command line settings of the interactive session
+#[derive(Debug, Clone, Default)]
+struct RunOptions {
+    alignment: Alignment,
+    script: Option<PathBuf>,
+    quiet: bool,
+    region: Option<String>,
+    scan_readonly: bool,
+    page_size: usize,
+    color: bool,
+    freeze_retries: usize,
+    max_results: Option<usize>,
+    changed_regions: bool,
+    /// unix socket to take JSON requests from instead of prompting
+    serve: Option<PathBuf>,
+    /// results carried over from a session of another type
+    addresses: Vec<usize>,
+    /// script lines already executed by a previous session
+    script_offset: usize,
 }
 
-pub struct StaticLocation {
-    pub map_path: String,
-    pub offset: usize,
-    pub base: usize,
+/// why an interactive session ended
+#[derive(Debug)]
+enum RunOutcome {
+    Quit,
+    /// the session continues as `variable_type`, with the same results
+    Reinterpret {
+        variable_type: String,
+        addresses: Vec<usize>,
+        script_offset: usize,
+    },
 }
 
-impl AddressInfo {
-    pub fn from_address<T: memory::ReadFromBytes>(
-        process: &ProcessQuery<T>,
-        pid: i32,
-        address: usize,
-    ) -> BetrayalResult<Self> {
-        let (info, _map) = process
-            .mappings()?
-            .into_iter()
-            .find(|(_info, map)| map.base <= address && address < map.ceiling)
-            .ok_or(BetrayalError::PartialRead)?;
-        Ok(info.clone())
-    }
-
-    pub fn is_static(&self) -> bool {
-        !self.writable
+/// the current page of results, in display order
+fn print_results<T: ReadFromBytes>(process: &ProcessQuery<T>) {
+    let pages = (process.results.len() + process.page_size - 1) / process.page_size;
+    let page = process.page.min(pages.saturating_sub(1));
+    if pages > 1 {
+        println!(
+            ":: found {} matches, page {}/{} (\"page <n>\" to switch)",
+            process.results.len(),
+            page,
+            pages - 1
+        );
     }
-
-    /// file with permission RW, either with a name, or directly following a named map (without a gap!!)
-    pub fn static_location(&self, pid: i32, address: usize) -> Option<StaticLocation> {
-        use procmaps::Path;
-        if !self.writable {
-            return None;
-        }
-        let maps = ProcessQuery::<u8>::mappings_all_with_unreadable(pid).ok()?;
-        let slice_index = match maps
-            .iter()
-            .find_position(|(_info, map)| map.base <= address && address < map.ceiling)
-        {
-            Some((map_index, (_, map))) => match &map.pathname {
-                Path::MappedFile(name)
-                    if name == "" && map.base == maps.get(map_index - 1)?.1.ceiling =>
-                {
-                    // this is a .bss, we start one address up
-                    Some(map_index - 1)
-                }
-                Path::MappedFile(_) => Some(map_index), // this is a normal mapped file, still we need to offset it to allow for lookups
-                _ => None,
+    for (index, (info, address, value)) in process
+        .displayed_results()
+        .into_iter()
+        .enumerate()
+        .skip(page * process.page_size)
+        .take(process.page_size)
+    {
+        let unreadable = process.unreadable.contains(address);
+        let delta = if unreadable {
+            "(unreadable)".to_string()
+        } else {
+            process.format_delta(*address, value)
+        };
+        let changed = !unreadable && !delta.is_empty() && delta != "(=)";
+        let value = format!("{} {}", process.format_value(value), delta);
+        let location = match info.static_location(process.pid, *address) {
+            Some(location) => format!(
+                "@STATIC[{}] (raw: {} + {})",
+                location, location.base, location.offset
+            ),
+            None => String::new(),
+        };
+        let line = format!(
+            "{}. {} (0x{:x}) -- {} {}{}",
+            index,
+            address,
+            address,
+            if process.color && changed {
+                value.green().to_string()
+            } else {
+                value
             },
-            None => None,
-        }?;
-
-        let maps = (&maps[..(slice_index + 1)]) // omit later entries
-            .iter()
-            .sorted_by_key(|(_, m)| m.base)
-            .rev() // go backwards
-            .collect::<Vec<_>>();
-        let static_base = maps
-            .iter()
-            .zip(maps.iter().skip(1)) // compare neighbours
-            .inspect(|((_, curr), (_, next))| {
-                // println!(
-                //     "({:x} - {:x}) :: {:?}\n({:x} - {:x}) :: {:?}",
-                //     curr.base, curr.ceiling, curr.pathname, next.base, next.ceiling, next.pathname
-                // )
-            })
-            .take_while(|((_, curr), (_, next))| curr.base == next.ceiling) // there can be no memory gap
-            .collect::<Vec<_>>()
-            .into_iter()
-            .map(|((_, curr), (_, _))| curr)
-            .group_by(|m| &m.pathname) // chunks of maps with the same path
-            .into_iter()
-            .next()
-            .map(|(_, v)| v)?
-            .last()?;
-
-        let path = &static_base.pathname;
-        match path {
-            procmaps::Path::MappedFile(path) => Some(StaticLocation {
-                map_path: path.clone(),
-                base: static_base.base,
-                offset: address - static_base.base,
-            }),
-            _ => None,
-        }
-    }
-}
-
-impl From<&Map> for AddressInfo {
-    fn from(m: &Map) -> Self {
-        Self {
-            writable: m.perms.writable,
+            match process.labels.get(address) {
+                Some(label) => format!("<{}> ", label),
+                None => String::new(),
+            },
+            if process.color {
+                location.yellow().to_string()
+            } else {
+                location
+            }
+        );
+        if process.color && !info.writable {
+            println!("{}", line.dimmed());
+        } else {
+            println!("{}", line);
         }
     }
 }
 
-pub fn find_equal_to<T: ReadFromBytes>(pid: i32, value: T) -> BetrayalResult<Vec<AddressValue<T>>> {
-    let mut process = ProcessQuery::<T>::new(pid);
-    process.perform_new_query(Filter::IsEqual(value))?;
-    Ok(process.results.into_iter().map(|(_k, v)| v).collect())
-}
-
-pub fn find_in_range<T: ReadFromBytes>(
+async fn run<T: 'static + ReadFromBytes>(
     pid: i32,
-    min: T,
-    max: T,
-) -> BetrayalResult<Vec<AddressValue<T>>> {
+    options: RunOptions,
+) -> Result<RunOutcome, Box<dyn std::error::Error>> {
+    let RunOptions {
+        alignment,
+        script,
+        quiet,
+        region,
+        scan_readonly,
+        page_size,
+        color,
+        freeze_retries,
+        max_results,
+        changed_regions,
+        serve,
+        addresses,
+        script_offset,
+    } = options;
     let mut process = ProcessQuery::<T>::new(pid);
-    process.perform_new_query(Filter::InRange((min, max)))?;
-    Ok(process.results.into_iter().map(|(_k, v)| v).collect())
-}
-
-use petgraph::graph::DiGraph;
-
-fn log_graph<T: ReadFromBytes + Serialize + TryFrom<usize>>(graph: &DiGraph<T, ()>, pid: i32) {
-    for edge in graph.node_indices() {
-        print!("[*]");
-        let mut dfs = petgraph::visit::Dfs::new(&graph, edge);
-
-        while let Some(visited) = dfs.next(&graph) {
-            let address = graph[visited];
-            print!(" -> {:?}", address);
-        }
-        println!();
-    }
-
-    println!();
-}
-
-pub fn build_pointer_tree<T: 'static + ReadFromBytes + Serialize + TryFrom<usize>>(
-    pid: i32,
-    tree: Arc<Mutex<DiGraph<T, ()>>>,
-    current: Option<NodeIndex>,
-    addresses: Vec<T>,
-    depth: T,
-) -> BetrayalResult<()> {
-    let mut tasks = vec![];
-    for address in addresses {
-        let tree = Arc::clone(&tree);
-        let a = {
-            let mut tree = tree.lock();
-            let a = tree.add_node(address);
-            if let Some(current) = current {
-                tree.add_edge(a, current, ());
+    process.alignment = alignment;
+    process.quiet = quiet;
+    process.region = region;
+    process.scan_readonly = scan_readonly;
+    process.page_size = page_size.max(1);
+    process.color = color;
+    process.freeze_retries = freeze_retries;
+    process.max_results = max_results;
+    process.changed_regions = changed_regions;
+    process.update_mappings()?;
+    if !addresses.is_empty() {
+        for address in addresses {
+            if let Ok(info) = AddressInfo::from_address(&process, pid, address) {
+                process
+                    .results
+                    .insert(address, (info, address, Default::default()));
             }
-            a
-        };
-
-        let addresses = find_in_range(pid, address - depth, address)?
-            .into_iter()
-            .filter_map(|(_, a, _)| a.try_into().ok())
-            .collect();
-        tasks.push(std::thread::spawn(move || {
-            build_pointer_tree(pid, tree, Some(a), addresses, depth)
-        }));
-    }
-    for task in tasks {
-        task.join().map_err(|e| {
-            BetrayalError::BadWrite(format!(
-                "thread crashed during pointer map building :: {:#?}",
-                e
-            ))
-        })??;
-    }
-    Ok(())
-}
-
-pub fn pointer_map<T: 'static + ReadFromBytes + Serialize + TryFrom<usize>>(
-    pid: i32,
-    address: T,
-    depth: T,
-) -> BetrayalResult<DiGraph<T, ()>> {
-    let graph = Default::default();
-    build_pointer_tree::<T>(pid, Arc::clone(&graph), None, vec![address], depth)?;
-    let graph = graph.lock().clone();
-    Ok(graph)
-}
-
-impl<T: ReadFromBytes> ProcessQuery<T> {
-    pub fn new(pid: i32) -> Self {
-        Self {
-            pid,
-            results: Default::default(),
-            mappings: Default::default(),
-        }
-    }
-
-    pub fn read_at(&mut self, pid: i32, address: usize) -> BetrayalResult<AddressValue<T>> {
-        if self.mappings.is_empty() {
-            self.update_mappings()?; // oof
         }
-        let (info, _map) = self
-            .mappings()?
-            .into_iter()
-            .find(|(info, m)| m.base <= address && address < m.ceiling)
-            .ok_or(BetrayalError::PartialRead)?;
-        let val = read_memory(pid, address, std::mem::size_of::<T>())?;
-        Ok((
-            info.clone(),
-            address,
-            T::read_value(val).map_err(|_e| BetrayalError::PartialRead)?,
-        ))
+        process.update_results()?;
     }
-
-    pub fn write_at(pid: i32, address: usize, value: T) -> BetrayalResult<()> {
-        let mut buffer = vec![];
-        value
-            .write_bytes(&mut buffer)
-            .map_err(|e| BetrayalError::BadWrite(format!("bad write: {}", e)))?;
-        write_memory(pid, address, buffer)?;
-        Ok(())
+    let process = Arc::new(Mutex::new(process));
+    if let Some(socket) = serve {
+        server::serve(process, &socket)?;
+        return Ok(RunOutcome::Quit);
     }
-
-    pub fn update_results(&mut self) -> BetrayalResult<()> {
-        let mut invalid_regions = vec![];
-        let mut results = self.results.clone();
-        {
-            for (address, result) in results.iter_mut() {
-                match self.read_at(self.pid, *address) {
-                    Ok(val) => *result = val,
-                    Err(_e) => invalid_regions.push(*address),
-                }
+    let freezes: FreezeRegistry<T> = Default::default();
+    println!("{}", HELP_TEXT);
+    println!(
+        " :: running in [{}] mode ({:?} alignment)",
+        std::any::type_name::<T>(),
+        alignment
+    );
+    let mut script = match script {
+        Some(path) => Some(read_lines(path)?.skip(script_offset)),
+        None => None,
+    };
+    let mut script_line = script_offset;
+    let mut outcome = RunOutcome::Quit;
+    // scripts are read line by line, only the interactive prompt gets history and editing
+    let mut editor = match script {
+        Some(_) => None,
+        None => {
+            let mut editor = Editor::<()>::new();
+            if let Some(path) = history_path() {
+                let _ = editor.load_history(&path);
             }
+            Some(editor)
         }
-        for index in invalid_regions.into_iter().rev() {
-            results.remove(&index);
+    };
+    loop {
+        if process::has_exited(pid) {
+            println!(" :: target process has exited ::");
+            break;
         }
-        self.results = results;
-
-        Ok(())
-    }
-
-    pub fn perform_write(&mut self, writer: Writer<T>) -> BetrayalResult<()> {
-        let (selected_address, value) = writer;
-        let (_info, address, _current_value) = self
-            .results
-            .get(&selected_address)
-            .ok_or(BetrayalError::BadWrite("no such address".to_string()))?;
-        Self::write_at(self.pid, *address, value)?;
-        self.update_results()?;
-        Ok(())
-    }
-
-    pub fn perform_new_query(&mut self, filter: Filter<T>) -> BetrayalResult<()> {
-        let results = self
-            .query(filter.clone())?
-            .into_par_iter()
-            .filter(|v| filter.clone().matches(*v, &self.results))
-            .map(|(info, address, value)| (address, (info, address, value)))
-            .collect();
-        self.results = results;
-        Ok(())
-    }
-    pub fn perform_query(&mut self, filter: Filter<T>) -> BetrayalResult<()> {
-        if self.results.is_empty() {
-            self.perform_new_query(filter.clone())?;
+        if script.is_some() && cancel::requested() {
+            println!(" :: script interrupted ::");
+            break;
         }
-        let current_results = self.results.clone();
-        self.update_results()?;
-        self.results
-            .retain(|_k, v| filter.clone().matches(*v, &current_results));
-
-        Ok(())
-    }
-
-    pub fn mappings_all_with_unreadable(pid: i32) -> BetrayalResult<Vec<(AddressInfo, Map)>> {
-        let mappings = std::mem::take(
-            procmaps::Mappings::from_pid(pid)
-                .map_err(|_e| BetrayalError::BadPid)?
-                .deref_mut(),
-        );
-        Ok(mappings
-            .into_iter()
-            .map(|m| {
-                (
-                    AddressInfo {
-                        writable: m.perms.writable,
-                    },
-                    m,
-                )
-            })
-            .collect())
-    }
-
-    pub fn mappings_all(pid: i32) -> BetrayalResult<Vec<(AddressInfo, Map)>> {
-        Ok(Self::mappings_all_with_unreadable(pid)?
-            .into_iter()
-            .collect())
-    }
-
-    fn mappings(&self) -> BetrayalResult<Box<impl Iterator<Item = &(AddressInfo, Map)>>> {
-        Ok(Box::new(self.mappings.iter()))
-    }
-
-    pub fn in_address_space(&self, value: i32) -> BetrayalResult<bool> {
-        Ok(self
-            .mappings()?
-            .into_iter()
-            .any(|(_info, map)| map.base as i32 <= value && value <= map.ceiling as i32))
-    }
-
-    pub fn update_mappings(&mut self) -> BetrayalResult<()> {
-        self.mappings = Self::mappings_all(self.pid)?;
-        Ok(())
-    }
-    fn query<'process, 'result>(
-        &'process mut self,
-        filter: Filter<T>,
-        // ) -> BetrayalResult<Box<impl Iterator<Item = QueryResult> + 'result>>
-    ) -> BetrayalResult<Vec<AddressValue<T>>>
-    where
-        'process: 'result,
-    {
-        self.update_mappings()?;
-
-        let pid = self.pid;
-        let mappings = self.mappings()?;
-        let mappings: Vec<_> = mappings
-            .into_iter()
-            .unique_by(|(_info, m)| m.base)
-            .unique_by(|(_info, m)| m.ceiling)
-            .collect();
-
-        let results: Arc<Mutex<Vec<AddressValue<T>>>> = Default::default();
-        mappings.into_par_iter().for_each(|(info, map)| {
-            let results = Arc::clone(&results);
-            let filter = filter.clone();
-            let dummy_results = Default::default(); // this should work for now cause this is only ran on the initial scan... I hope
-            let mut results_chunk = match read_memory(pid, map.base, map.ceiling - map.base) {
-                Ok(m) => T::possible_values(&m[..], map.base)
-                    .map(|(address, value)| (info.clone(), address, value))
-                    .filter(|result| filter.clone().matches(*result, &dummy_results))
-                    .collect(),
-                Err(_e) => {
-                    vec![]
-                }
-            };
-            results.lock().append(&mut results_chunk);
-        });
-
-        println!(" :: scanning done ::");
-        let results = results.lock().clone();
-        Ok(results)
-    }
-}
-
-async fn run<T: 'static + ReadFromBytes>(
-    pid: i32,
-    tasks: &mut Vec<JoinHandle<()>>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut process = ProcessQuery::<T>::new(pid);
-    process.update_mappings()?;
-    let process = Arc::new(Mutex::new(process));
-    println!("{}", HELP_TEXT);
-    println!(" :: running in [{}] mode", std::any::type_name::<T>());
-    loop {
+        cancel::reset();
         let process = Arc::clone(&process);
-        let input = take_input::<Command<T>>("");
+        let input = match script.as_mut() {
+            Some(lines) => match lines.next() {
+                Some(line) => {
+                    let line = line?;
+                    script_line += 1;
+                    println!("\n >> {}", line);
+                    // trailing whitespace can be a part of the "wstr" text
+                    Command::<T>::from_str(line.trim_start())
+                }
+                None => break,
+            },
+            None => {
+                let editor = editor
+                    .as_mut()
+                    .expect("interactive sessions have an editor");
+                println!();
+                match editor.readline(" >> ") {
+                    Ok(line) => {
+                        editor.add_history_entry(line.as_str());
+                        Command::<T>::from_str(line.trim_start())
+                    }
+                    Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                    Err(e) => {
+                        eprintln!(" :: [ERR] :: failed to read the command :: {}", e);
+                        break;
+                    }
+                }
+            }
+        };
 
         match input {
             Ok(command) => match command {
                 Command::Quit => break,
+                Command::Reinterpret(variable_type) => {
+                    let addresses = process.lock().results.keys().copied().collect::<Vec<_>>();
+                    println!(
+                        " :: reinterpreting {} results as {}",
+                        addresses.len(),
+                        variable_type
+                    );
+                    outcome = RunOutcome::Reinterpret {
+                        variable_type,
+                        addresses,
+                        script_offset: script_line,
+                    };
+                    break;
+                }
                 Command::Help => {
                     println!("{}", HELP_TEXT);
                     continue;
                 }
 
-                Command::Refresh => process.lock().update_results()?,
-                Command::PerformFilter(filter) => process.lock().perform_query(filter)?,
-                Command::Write(writer) => process.lock().perform_write(writer)?,
-                Command::KeepWriting(writer) => {
-                    let process = Arc::clone(&process);
-                    tasks.push(std::thread::spawn(move || loop {
-                        match process.lock().perform_write(writer) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                eprintln!(
-                                    " :: [ERR] :: Writer thread crashed with {}. Aborting.",
-                                    e
-                                );
-                                break;
+                Command::Refresh => {
+                    process.lock().update_results_keeping_unreadable()?;
+                }
+                Command::RefreshPrune => match process.lock().update_results_pruning() {
+                    Ok(dropped) => println!(" :: dropped {} unreadable addresses", dropped),
+                    Err(e) => {
+                        eprintln!(" :: ERR :: {}", e);
+                        continue;
+                    }
+                },
+                Command::DeleteAddress(index) => match process.lock().delete_at_index(index) {
+                    Ok((_info, address, value)) => {
+                        println!(" :: removed {} (0x{:x}) -- {}", address, address, value)
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+                Command::Compare(a, b) => {
+                    let mut process = process.lock();
+                    match process.compare_at_indices(a, b) {
+                        Ok(((_, a_address, a_value), (_, b_address, b_value))) => println!(
+                            " :: 0x{:x} = {}, 0x{:x} = {} :: {}",
+                            a_address,
+                            process.format_value(&a_value),
+                            b_address,
+                            process.format_value(&b_value),
+                            match format_difference(a_value, b_value).as_deref() {
+                                Some("=") => "equal".to_string(),
+                                Some(difference) => format!("not equal ({})", difference),
+                                None => "not comparable".to_string(),
                             }
-                        };
-
-                        std::thread::sleep(std::time::Duration::from_millis(50));
-                    }));
+                        ),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                    continue;
+                }
+                Command::Label(index, label) => {
+                    if let Err(e) = process.lock().label_at_index(index, label) {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                }
+                Command::ToggleHex => {
+                    let mut process = process.lock();
+                    process.hex_display = !process.hex_display;
+                    println!(
+                        " :: hex display {}",
+                        if process.hex_display { "on" } else { "off" }
+                    );
+                }
+                Command::Undo => match process.lock().undo() {
+                    Ok(left) => println!(" :: undone, {} more snapshots left", left),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+                Command::Clear => {
+                    println!(" :: dropped {} results", process.lock().clear());
+                    continue;
+                }
+                Command::Dedupe => {
+                    println!(" :: dropped {} duplicates", process.lock().dedupe())
+                }
+                Command::PerformFilter(filter) => {
+                    let mut process = process.lock();
+                    match process.perform_query(filter) {
+                        Ok(_) => process.page = 0,
+                        Err(BetrayalError::Cancelled) => {
+                            println!(" :: scan cancelled, results unchanged");
+                            continue;
+                        }
+                        Err(e @ BetrayalError::TooManyResults(_)) => {
+                            eprintln!(" :: ERR :: {}", e);
+                            continue;
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                Command::FindCopies(index) => {
+                    let mut process = process.lock();
+                    match process.perform_copies_query(index) {
+                        Ok(value) => println!(" :: addresses holding {}", value),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    }
+                    process.page = 0;
+                }
+                Command::Page(page) => process.lock().page = page,
+                Command::Sort(sort) => process.lock().sort = sort,
+                Command::PageSize(page_size) => {
+                    let mut process = process.lock();
+                    process.page_size = page_size.max(1);
+                    process.page = 0;
+                }
+                Command::PerformUnknownScan => {
+                    let mut process = process.lock();
+                    let candidates = process.unknown_candidate_count()?;
+                    if candidates > UNKNOWN_SCAN_CONFIRMATION_THRESHOLD {
+                        let answer = take_input::<String>(&format!(
+                            " :: this will snapshot {} values, continue? [y/N]",
+                            candidates
+                        ))
+                        .unwrap_or_default();
+                        if answer != "y" {
+                            println!(" :: unknown value scan aborted");
+                            continue;
+                        }
+                    }
+                    match process.perform_unknown_query() {
+                        Ok(_) => {}
+                        Err(BetrayalError::Cancelled) => {
+                            println!(" :: snapshot cancelled, results unchanged");
+                            continue;
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                Command::ChangedIn(region) => {
+                    let mut process = process.lock();
+                    match process.perform_changed_in(&region) {
+                        Ok(true) => println!(
+                            " :: snapshot of {:?} taken, change something and run it again",
+                            region
+                        ),
+                        Ok(false) => {}
+                        Err(BetrayalError::Cancelled) => {
+                            println!(" :: scan cancelled, results unchanged");
+                            continue;
+                        }
+                        Err(e) => {
+                            eprintln!(" :: ERR :: {}", e);
+                            continue;
+                        }
+                    }
+                    process.page = 0;
+                }
+                Command::Write(writer) => process.lock().perform_write(writer)?,
+                Command::WriteAddress(address, value) => {
+                    if let Err(e) = process.lock().perform_write_literal(address, value) {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                }
+                Command::WriteBytes(address, bytes) => {
+                    let len = bytes.len();
+                    match process.lock().perform_write_bytes(address, bytes) {
+                        Ok(_) => println!(" :: wrote {} bytes to 0x{:x}", len, address),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    }
+                }
+                Command::WriteAll(value) => match process.lock().perform_write_all(value) {
+                    Ok(count) => println!(" :: wrote {} to {} addresses", value, count),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                },
+                Command::KeepWritingAll(value) => {
+                    let addresses = process.lock().results.keys().copied().collect::<Vec<_>>();
+                    for address in &addresses {
+                        freeze(&process, &freezes, *address, value);
+                    }
+                    println!(" :: froze {} addresses at {}", addresses.len(), value);
+                }
+                Command::KeepWriting((index, value)) => {
+                    let address = match process.lock().address_at_index(index) {
+                        Ok(address) => address,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+                    freeze(&process, &freezes, address, value);
+                }
+                Command::ListFrozen => {
+                    let process = process.lock();
+                    let freezes = freezes.lock();
+                    let mut frozen = freezes.iter().collect::<Vec<_>>();
+                    frozen.sort_by_key(|(address, _handle)| **address);
+                    for (index, (address, handle)) in frozen.into_iter().enumerate() {
+                        println!(
+                            " :: [{}] frozen {} (0x{:x}) -- {} {}",
+                            index,
+                            address,
+                            address,
+                            handle.value,
+                            process.labels.get(address).cloned().unwrap_or_default()
+                        );
+                    }
+                    continue;
+                }
+                Command::Unfreeze(index) => {
+                    // the index is the one "frozen" lists, the address doesn't have to be a result
+                    let mut freezes = freezes.lock();
+                    let mut addresses = freezes.keys().copied().collect::<Vec<_>>();
+                    addresses.sort_unstable();
+                    let address = match addresses.get(index) {
+                        Some(address) => *address,
+                        None => {
+                            eprintln!(" :: nothing frozen at index {}", index);
+                            continue;
+                        }
+                    };
+                    if let Some(handle) = freezes.remove(&address) {
+                        handle.stop();
+                    }
                 }
                 Command::AddAddress(address) => {
                     let mut process = process.lock();
@@ -549,11 +533,16 @@ async fn run<T: 'static + ReadFromBytes>(
                             continue;
                         }
                     };
-                    process
-                        .results
-                        .insert(address, (info, address, Default::default()));
+                    process.insert_results(info, address..address + 1);
                     process.update_results()?;
                 }
+                Command::Only(address) => match process.lock().only(address) {
+                    Ok(value) => println!(" :: watching only 0x{:x} -- {}", address, value),
+                    Err(e) => {
+                        eprintln!("error while adding address :: {}", e);
+                        continue;
+                    }
+                },
                 Command::AddAddressRange(start, end) => {
                     println!(" :: adding {} - {}", start, end);
                     let mut process = process.lock();
@@ -564,17 +553,16 @@ async fn run<T: 'static + ReadFromBytes>(
                             continue;
                         }
                     };
-                    for address in start..end {
-                        process
-                            .results
-                            .insert(address, (info, address, Default::default()));
-                    }
+                    process.insert_results(info, start..end);
                     process.update_results()?;
                 }
-                Command::PointerMapU32(address, depth) => {
-                    println!(" :: building a pointer32 map for {}", address);
+                Command::PointerMapU32(address, window, levels) => {
+                    println!(
+                        " :: building a pointer32 map for {} (window {}, {} levels)",
+                        address, window, levels
+                    );
                     let pid = { process.lock().pid };
-                    let mut map = match pointer_map::<u32>(pid, address, depth) {
+                    let mut map = match pointer_map::<u32>(pid, address, window, levels) {
                         Ok(map) => map,
                         Err(e) => {
                             println!(" :: ERR :: {}", e);
@@ -582,12 +570,16 @@ async fn run<T: 'static + ReadFromBytes>(
                         }
                     };
                     println!(" :: SUCCESS ::",);
-                    log_graph(&mut map, pid)
+                    log_graph(&mut map, pid);
+                    process.lock().pointer_map = Some(PointerMap::U32(map));
                 }
-                Command::PointerMapU64(address, depth) => {
-                    println!(" :: building a pointer64 map for {}", address);
+                Command::PointerMapU64(address, window, levels) => {
+                    println!(
+                        " :: building a pointer64 map for {} (window {}, {} levels)",
+                        address, window, levels
+                    );
                     let pid = { process.lock().pid };
-                    let mut map = match pointer_map::<u64>(pid, address, depth) {
+                    let mut map = match pointer_map::<u64>(pid, address, window, levels) {
                         Ok(map) => map,
                         Err(e) => {
                             println!(" :: ERR :: {}", e);
@@ -595,7 +587,286 @@ async fn run<T: 'static + ReadFromBytes>(
                         }
                     };
                     println!(" :: SUCCESS ::",);
-                    log_graph(&mut map, pid)
+                    log_graph(&mut map, pid);
+                    process.lock().pointer_map = Some(PointerMap::U64(map));
+                }
+                Command::FindNeighbourValues(query) => {
+                    match process.lock().find_neighbour_values(query) {
+                        Ok(found) => {
+                            println!(" :: {} matching windows", found.len());
+                            for neighbours in found {
+                                println!(
+                                    "{}",
+                                    neighbours
+                                        .values
+                                        .iter()
+                                        .map(|(_info, address, value)| {
+                                            format!("0x{:x} = {}", address, value)
+                                        })
+                                        .join(", ")
+                                );
+                            }
+                        }
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
+                }
+                Command::PointerChains(address) => {
+                    let chains = match &process.lock().pointer_map {
+                        Some(map) => map.chains_to(address),
+                        None => {
+                            eprintln!(" :: no pointer map yet, build one with \"p m\" first");
+                            continue;
+                        }
+                    };
+                    println!(" :: {} pointer chains to 0x{:x}", chains.len(), address);
+                    for chain in chains {
+                        println!(
+                            "{}",
+                            chain.iter().map(|a| format!("0x{:x}", a)).join(" -> ")
+                        );
+                    }
+                    continue;
+                }
+                Command::StaticPointerChains => {
+                    let process = process.lock();
+                    let chains = match &process.pointer_map {
+                        Some(map) => map.static_chains(process.pid),
+                        None => {
+                            eprintln!(" :: no pointer map yet, build one with \"p m\" first");
+                            continue;
+                        }
+                    };
+                    match chains {
+                        Ok(chains) => {
+                            println!(" :: {} static pointer chains", chains.len());
+                            for chain in chains {
+                                println!("{}", chain);
+                            }
+                        }
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
+                }
+                Command::Watch(interval) => {
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let watcher = {
+                        let process = Arc::clone(&process);
+                        let stop = Arc::clone(&stop);
+                        std::thread::spawn(move || {
+                            while !stop.load(Ordering::Relaxed) {
+                                {
+                                    let mut process = process.lock();
+                                    if let Err(e) = process.update_results() {
+                                        eprintln!(" :: [ERR] :: watch stopped: {}", e);
+                                        break;
+                                    }
+                                    println!("\n :: every {}ms, press enter to stop ::", interval);
+                                    print_results(&process);
+                                }
+                                std::thread::sleep(std::time::Duration::from_millis(interval));
+                            }
+                        })
+                    };
+                    let _ = take_input::<String>("");
+                    stop.store(true, Ordering::Relaxed);
+                    if watcher.join().is_err() {
+                        eprintln!(" :: [ERR] :: watch thread panicked");
+                    }
+                    continue;
+                }
+                Command::Resolve(base, offsets) => {
+                    let mut process = process.lock();
+                    let resolved = reclass::scripting::calculate_address(pid, &base)
+                        .and_then(|base| resolve_pointer_chain(pid, base, &offsets))
+                        .and_then(|address| process.read_at(pid, address));
+                    match resolved {
+                        Ok((_info, address, value)) => println!(
+                            " :: {} (0x{:x}) -- {}",
+                            address,
+                            address,
+                            process.format_value(&value)
+                        ),
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
+                }
+                #[cfg(target_arch = "x86_64")]
+                Command::WatchWrites(index) => {
+                    let address = match process.lock().address_at_index(index) {
+                        Ok(address) => address,
+                        Err(e) => {
+                            eprintln!(" :: ERR :: {}", e);
+                            continue;
+                        }
+                    };
+                    println!(
+                        " :: watching writes to 0x{:x}, press enter to stop ::",
+                        address
+                    );
+                    let stop = Arc::new(AtomicBool::new(false));
+                    let watcher = {
+                        let stop = Arc::clone(&stop);
+                        std::thread::spawn(move || {
+                            betrayal_engine::watchpoint::watch_writes(
+                                pid,
+                                address,
+                                std::mem::size_of::<T>(),
+                                &stop,
+                                |tid, ip| {
+                                    println!(
+                                        " :: thread {} wrote, instruction before 0x{:x}",
+                                        tid, ip
+                                    )
+                                },
+                            )
+                        })
+                    };
+                    let _ = take_input::<String>("");
+                    stop.store(true, Ordering::Relaxed);
+                    match watcher.join() {
+                        Ok(Ok(hits)) => {
+                            println!(" :: {} writing instructions", hits.len());
+                            for (ip, count) in hits {
+                                println!("0x{:x} :: {} hits", ip, count);
+                            }
+                        }
+                        Ok(Err(e)) => eprintln!(" :: ERR :: {}", e),
+                        Err(_) => eprintln!(" :: [ERR] :: watchpoint thread panicked"),
+                    }
+                    continue;
+                }
+                Command::NearPointers(target, slack) => {
+                    match find_near_pointers(pid, target, slack) {
+                        Ok(pointers) => {
+                            println!(
+                                " :: {} pointers to 0x{:x} - {}",
+                                pointers.len(),
+                                target,
+                                slack
+                            );
+                            for (address, offset) in pointers {
+                                println!("0x{:x} -> +0x{:x}", address, offset);
+                            }
+                        }
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
+                }
+                Command::Guess(address) => {
+                    let bytes = match read_memory(pid, address, 8).and_then(|bytes| {
+                        <[u8; 8]>::try_from(bytes).map_err(|_| BetrayalError::PartialRead)
+                    }) {
+                        Ok(bytes) => bytes,
+                        Err(e) => {
+                            eprintln!(" :: ERR :: {}", e);
+                            continue;
+                        }
+                    };
+                    let mut process = process.lock();
+                    if let Err(e) = process.update_mappings() {
+                        eprintln!(" :: ERR :: {}", e);
+                        continue;
+                    }
+                    for interpretation in memory::guess_types(&bytes, |pointer| {
+                        process.in_address_space(pointer).unwrap_or(false)
+                    }) {
+                        println!(
+                            "{:>8} :: {}{}",
+                            interpretation.type_name,
+                            interpretation.value,
+                            if interpretation.likely {
+                                "  <- likely"
+                            } else {
+                                ""
+                            }
+                        );
+                    }
+                    continue;
+                }
+                Command::Maps(filter) => {
+                    let maps = match ProcessQuery::<T>::mappings_all(pid) {
+                        Ok(maps) => maps,
+                        Err(e) => {
+                            eprintln!(" :: ERR :: {}", e);
+                            continue;
+                        }
+                    };
+                    for (_info, map) in maps {
+                        let name = map_name(&map);
+                        if let Some(filter) = &filter {
+                            if !name.contains(filter.as_str()) {
+                                continue;
+                            }
+                        }
+                        let flag = |set: bool, flag: char| if set { flag } else { '-' };
+                        println!(
+                            "0x{:<12x} - 0x{:<12x} {:>10} {}{}{} {}",
+                            map.base,
+                            map.ceiling,
+                            helpers::human_bytes(map.ceiling - map.base),
+                            flag(map.perms.readable, 'r'),
+                            flag(map.perms.writable, 'w'),
+                            flag(map.perms.executable, 'x'),
+                            name
+                        );
+                    }
+                    continue;
+                }
+                Command::Dump(address, len) => {
+                    match read_memory(pid, address, len) {
+                        Ok(bytes) => println!("{}", helpers::hexdump(address, &bytes)),
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
+                }
+                Command::ExportCsv(path) => {
+                    match std::fs::write(&path, process.lock().results_csv()) {
+                        Ok(_) => println!(" :: results written to {:?}", path),
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
+                }
+                Command::Diff(before, after) => {
+                    let diff = match snapshot::load_snapshot::<T>(&before).and_then(|before| {
+                        Ok(snapshot::diff_snapshots(
+                            &before,
+                            &snapshot::load_snapshot(&after)?,
+                        ))
+                    }) {
+                        Ok(diff) => diff,
+                        Err(e) => {
+                            eprintln!(" :: ERR :: {}", e);
+                            continue;
+                        }
+                    };
+                    println!(" :: {} changed", diff.changed.len());
+                    for (address, old, new) in diff.changed {
+                        println!("0x{:x} :: {} -> {}", address, old, new);
+                    }
+                    println!(" :: {} added", diff.added.len());
+                    for (address, value) in diff.added {
+                        println!("0x{:x} :: {}", address, value);
+                    }
+                    println!(" :: {} removed", diff.removed.len());
+                    for (address, value) in diff.removed {
+                        println!("0x{:x} :: {}", address, value);
+                    }
+                    continue;
+                }
+                Command::ExportPointerMap(path) => {
+                    let dot = match &process.lock().pointer_map {
+                        Some(map) => map.to_dot(),
+                        None => {
+                            eprintln!(" :: no pointer map yet, build one with \"p m\" first");
+                            continue;
+                        }
+                    };
+                    match std::fs::write(&path, dot) {
+                        Ok(_) => println!(" :: pointer map written to {:?}", path),
+                        Err(e) => eprintln!(" :: ERR :: {}", e),
+                    }
+                    continue;
                 }
             },
             Err(e) => {
@@ -604,36 +875,19 @@ async fn run<T: 'static + ReadFromBytes>(
             }
         };
 
-        if process.lock().results.len() > 50 {
-            println!(":: found {} matches", process.lock().results.len());
-        } else {
-            let process = process.lock();
-            for (index, (_, (info, address, value))) in process.results.iter().enumerate() {
-                println!(
-                    "{}. {} (0x{:x}) -- {} {}",
-                    index,
-                    address,
-                    address,
-                    value,
-                    match info.static_location(process.pid, *address) {
-                        Some(location) => format!(
-                            "@STATIC[static_address(PID,\"{}\")+{}] (raw: {} + {})",
-                            location.map_path, location.offset, location.base, location.offset
-                        ),
-                        None => String::new(),
-                    } // if info.is_static() {
-                      //     match
-                      //     let location = info.static_location(process.pid, *address);
-                      //     format!("@STATIC()")
+        print_results(&process.lock());
+    }
 
-                      // } else { String::new() }
-                );
-            }
+    for (_address, handle) in freezes.lock().drain() {
+        handle.stop();
+    }
+    if let (Some(editor), Some(path)) = (editor.as_mut(), history_path()) {
+        if let Err(e) = editor.save_history(&path) {
+            eprintln!(" :: [WARN] :: failed to save command history :: {}", e);
         }
     }
-
     println!("{:#?}", process);
-    Ok(())
+    Ok(outcome)
 }
 
 #[tokio::main]
@@ -645,48 +899,196 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .subcommand(
             App::new("reclass")
                 .about("reclass-like interface for finding structs")
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .value_name("PATH")
+                        .about("struct layout to load and watch, it's created if it doesn't exist (a temporary file is used otherwise)"),
+                )
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .value_name("MS")
+                        .default_value("1000")
+                        .about("re-reads the values every <MS> milliseconds even if the layout didn't change, 0 only refreshes on edits"),
+                )
         )
         .arg(
             Arg::new("pid")
                 .short('p')
                 .long("pid")
                 .value_name("INT")
-                .required(true)
+                .required_unless_present("name")
+                .conflicts_with("name")
                 .about("PID of the process you're interested in analyzing"),
         )
+        .arg(
+            Arg::new("name")
+                .short('n')
+                .long("name")
+                .value_name("STRING")
+                .about("attach to a process whose name contains this string instead of specifying the PID"),
+        )
         .arg(
             Arg::new("variable_type")
                 .short('t')
                 .long("variable_type")
-                .value_name("u8 | u16 | u16 | i32 | u32 | i64 | u64 | f32 | f64")
+                .value_name("i8 | u8 | i16 | u16 | i32 | u32 | i64 | u64 | f32 | f64")
                 .default_value("i32")
-                .about("currently you need to specify the format up front and only use that until the end of the program. but hey, you can always run multiple instances of this thing. oh yeah and i32 is 32 bits signed, equivalent of 4 bytes in other software"),
+                .about("the format the values are read as, it can be switched later with \"reinterpret <type>\" (keeping the results). oh yeah and i32 is 32 bits signed, equivalent of 4 bytes in other software"),
+        )
+        .arg(
+            Arg::new("alignment")
+                .short('a')
+                .long("alignment")
+                .value_name("1 | type")
+                .default_value("type")
+                .about("only scan addresses aligned to the size of the variable type (type), or every single byte (1), useful for packed structs"),
+        )
+        .arg(
+            Arg::new("script")
+                .short('s')
+                .long("script")
+                .value_name("FILE")
+                .about("runs commands from a file (one per line) instead of prompting, quits at the end of the file"),
+        )
+        .arg(
+            Arg::new("region")
+                .short('r')
+                .long("region")
+                .value_name("STRING")
+                .about("only scan memory mapped from files whose path contains this string, eg. the game's executable or one of its libraries"),
+        )
+        .arg(
+            Arg::new("endian")
+                .short('e')
+                .long("endian")
+                .value_name("native | little | big")
+                .default_value("native")
+                .about("byte order of the analyzed process, eg. big for big-endian programs running under qemu-user"),
+        )
+        .arg(
+            Arg::new("scan_readonly")
+                .long("scan-readonly")
+                .about("also scan read-only memory, by default only writable maps are scanned for values"),
+        )
+        .arg(
+            Arg::new("page_size")
+                .long("page-size")
+                .value_name("INT")
+                .default_value("50")
+                .about("how many results are displayed at once, switch pages with \"page <n>\""),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .about("don't report progress of memory scans, useful for scripted runs"),
+        )
+        .arg(
+            Arg::new("serve")
+                .long("serve")
+                .value_name("SOCKET")
+                .conflicts_with("script")
+                .about("takes line-delimited JSON requests on a unix socket instead of prompting, for GUIs and other frontends (see src/server.rs)"),
+        )
+        .arg(
+            Arg::new("freeze_retries")
+                .long("freeze-retries")
+                .value_name("INT")
+                .default_value("20")
+                .about("how many writes in a row a frozen value can fail (eg. during a level load) before it's unfrozen"),
+        )
+        .arg(
+            Arg::new("max_results")
+                .long("max-results")
+                .value_name("INT")
+                .about("aborts a fresh scan that matches more than that many addresses (eg. \"e 0\"), instead of running out of memory"),
+        )
+        .arg(
+            Arg::new("changed_regions")
+                .long("changed-regions")
+                .about("\"f unknown\" checksums what it snapshots, refreshes then read memory in bulk and only decode what changed (helps with millions of results)"),
+        )
+        .arg(
+            Arg::new("color")
+                .long("color")
+                .value_name("auto | always | never")
+                .default_value("auto")
+                .about("colors static, changed and read-only results, auto does it only in a terminal (and respects NO_COLOR)"),
         )
         .get_matches();
-    let pid = matches.value_of_t_or_exit("pid");
+    let pid = match matches.value_of("name") {
+        Some(name) => process::resolve_pid_by_name(name)?,
+        None => matches.value_of_t_or_exit("pid"),
+    };
     println!("PID: {}", pid);
-    if let Some(ref _matches) = matches.subcommand_matches("reclass") {
-        reclass::run::run(pid)?;
+    matches
+        .value_of_t_or_exit::<memory::Endian>("endian")
+        .set_current();
+    if let Some(reclass_matches) = matches.subcommand_matches("reclass") {
+        let refresh = match reclass_matches.value_of_t_or_exit::<u64>("refresh") {
+            0 => None,
+            ms => Some(std::time::Duration::from_millis(ms)),
+        };
+        reclass::run::run(
+            pid,
+            reclass_matches.value_of("config").map(PathBuf::from),
+            refresh,
+        )?;
         std::process::exit(0);
     }
-    let mut tasks = vec![];
-    match matches.value_of("variable_type") {
-        Some(t) => match t.trim() {
-            "u8" => run::<u8>(pid, &mut tasks).await?,
-            "i16" => run::<i16>(pid, &mut tasks).await?,
-            "u16" => run::<u16>(pid, &mut tasks).await?,
-            "i32" => run::<i32>(pid, &mut tasks).await?,
-            "u32" => run::<u32>(pid, &mut tasks).await?,
-            "i64" => run::<i64>(pid, &mut tasks).await?,
-            "u64" => run::<u64>(pid, &mut tasks).await?,
-            "f32" => run::<f32>(pid, &mut tasks).await?,
-            "f64" => run::<f64>(pid, &mut tasks).await?,
-            _ => panic!("unsupported variable type"),
+    let mut options = RunOptions {
+        alignment: matches.value_of_t_or_exit("alignment"),
+        script: matches.value_of("script").map(PathBuf::from),
+        quiet: matches.is_present("quiet"),
+        region: matches.value_of("region").map(String::from),
+        scan_readonly: matches.is_present("scan_readonly"),
+        page_size: matches.value_of_t_or_exit("page_size"),
+        color: matches.value_of_t_or_exit::<ColorChoice>("color").enabled(),
+        freeze_retries: matches.value_of_t_or_exit("freeze_retries"),
+        max_results: if matches.is_present("max_results") {
+            Some(matches.value_of_t_or_exit("max_results"))
+        } else {
+            None
         },
+        changed_regions: matches.is_present("changed_regions"),
+        serve: matches.value_of("serve").map(PathBuf::from),
+        ..Default::default()
+    };
+    cancel::install()?;
+    let mut variable_type = match matches.value_of("variable_type") {
+        Some(t) => t.trim().to_string(),
         None => {
             panic!("variable_type is required");
         }
+    };
+    loop {
+        let outcome = match variable_type.as_str() {
+            "i8" => run::<i8>(pid, options.clone()).await?,
+            "u8" => run::<u8>(pid, options.clone()).await?,
+            "i16" => run::<i16>(pid, options.clone()).await?,
+            "u16" => run::<u16>(pid, options.clone()).await?,
+            "i32" => run::<i32>(pid, options.clone()).await?,
+            "u32" => run::<u32>(pid, options.clone()).await?,
+            "i64" => run::<i64>(pid, options.clone()).await?,
+            "u64" => run::<u64>(pid, options.clone()).await?,
+            "f32" => run::<f32>(pid, options.clone()).await?,
+            "f64" => run::<f64>(pid, options.clone()).await?,
+            _ => panic!("unsupported variable type"),
+        };
+        match outcome {
+            RunOutcome::Quit => break,
+            RunOutcome::Reinterpret {
+                variable_type: next,
+                addresses,
+                script_offset,
+            } => {
+                variable_type = next;
+                options.addresses = addresses;
+                options.script_offset = script_offset;
+            }
+        }
     }
-    run::<i32>(pid, &mut tasks).await?;
     Ok(())
 }
@@ -1,4 +1,5 @@
-use byteorder::{NativeEndian, ReadBytesExt, WriteBytesExt};
+use crate::error::BetrayalError;
+use byteorder::{BigEndian, LittleEndian, NativeEndian, ReadBytesExt, WriteBytesExt};
 use std::ops::{Add, Sub};
 use std::{
     io::{Cursor, Write},
@@ -6,15 +7,449 @@ use std::{
 };
 
 use std::cmp::{PartialEq, PartialOrd};
+use std::sync::atomic::{AtomicU8, Ordering};
 
 pub type AddressEntry<T> = (usize, T);
 
+/// which offsets are considered when scanning memory for values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// every single byte, useful for packed structs
+    Byte,
+    /// only offsets that are a multiple of the scanned type's size
+    Type,
+}
+
+impl Alignment {
+    pub fn step<T>(&self) -> usize {
+        match self {
+            Self::Byte => 1,
+            Self::Type => std::mem::size_of::<T>(),
+        }
+    }
+}
+
+impl Default for Alignment {
+    fn default() -> Self {
+        Self::Type
+    }
+}
+
+impl FromStr for Alignment {
+    type Err = BetrayalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(Self::Byte),
+            "type" => Ok(Self::Type),
+            other => Err(BetrayalError::BadCommand(format!(
+                "alignment must be either 1 or type, got {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// byte order of multi-byte values in the analyzed process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Native,
+    Little,
+    Big,
+}
+
+/// it's a global rather than a parameter of `ReadFromBytes` methods, it's picked once on the command line
+/// and threading it through would touch every read and write in the program
+static ENDIAN: AtomicU8 = AtomicU8::new(Endian::Native as u8);
+
+impl Endian {
+    pub fn current() -> Self {
+        match ENDIAN.load(Ordering::Relaxed) {
+            v if v == Self::Little as u8 => Self::Little,
+            v if v == Self::Big as u8 => Self::Big,
+            _ => Self::Native,
+        }
+    }
+
+    /// changes the byte order of every following read and write
+    pub fn set_current(self) {
+        ENDIAN.store(self as u8, Ordering::Relaxed);
+    }
+}
+
+impl FromStr for Endian {
+    type Err = BetrayalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "native" => Ok(Self::Native),
+            "little" => Ok(Self::Little),
+            "big" => Ok(Self::Big),
+            other => Err(BetrayalError::BadCommand(format!(
+                "endian must be one of native, little or big, got {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// calls a byteorder method with the byte order picked by `Endian`
+macro_rules! with_endian {
+    ($endian:expr, $target:expr, $method:ident $(, $arg:expr)*) => {
+        match $endian {
+            Endian::Native => $target.$method::<NativeEndian>($($arg),*),
+            Endian::Little => $target.$method::<LittleEndian>($($arg),*),
+            Endian::Big => $target.$method::<BigEndian>($($arg),*),
+        }
+    };
+}
+
+/// byte pattern where `None` is a wildcard (`??` in the command)
+pub type BytePattern = Vec<Option<u8>>;
+
+/// finds every place in `memory` where the bytes match `pattern`, value is read from the start of the match
+pub fn find_byte_pattern<T: ReadFromBytes>(
+    memory: &[u8],
+    base: usize,
+    pattern: &[Option<u8>],
+) -> Vec<AddressEntry<T>> {
+    if pattern.is_empty() || memory.len() < pattern.len() {
+        return vec![];
+    }
+    let size = std::mem::size_of::<T>();
+    memory
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_start, window)| {
+            window
+                .iter()
+                .zip(pattern.iter())
+                .all(|(byte, expected)| expected.map(|e| e == *byte).unwrap_or(true))
+        })
+        .map(|(start, _window)| {
+            let value = memory
+                .get(start..start + size)
+                .and_then(|bytes| T::read_value(bytes.to_vec()).ok())
+                .unwrap_or_default();
+            (base + start, value)
+        })
+        .collect()
+}
+
+/// `value` is within `epsilon` of `target`, for integers this is just a +/- window
+pub fn approximately_equal<T: ReadFromBytes>(value: T, target: T, epsilon: T) -> bool {
+    // subtracting the smaller one so that unsigned types don't underflow
+    let difference = if value > target {
+        value - target
+    } else {
+        target - value
+    };
+    difference <= epsilon
+}
+
+/// finds every place in `memory` where three consecutive values are close to `targets`
+/// (eg. x / y / z coordinates), value is the first one of the three
+pub fn find_triplets<T: ReadFromBytes>(
+    memory: &[u8],
+    base: usize,
+    step: usize,
+    targets: &[T; 3],
+    epsilon: T,
+) -> Vec<AddressEntry<T>> {
+    let size = std::mem::size_of::<T>();
+    if memory.len() < size * 3 {
+        return vec![];
+    }
+    memory
+        .windows(size * 3)
+        .enumerate()
+        .step_by(step)
+        .filter_map(|(start, window)| {
+            let values = window
+                .chunks(size)
+                .map(|bytes| T::read_value(bytes.to_vec()).ok())
+                .collect::<Option<Vec<_>>>()?;
+            values
+                .iter()
+                .zip(targets.iter())
+                .all(|(value, target)| approximately_equal(*value, *target, epsilon))
+                .then(|| (base + start, values[0]))
+        })
+        .collect()
+}
+
+/// one way of reading a few bytes of unknown type, see `guess_types`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interpretation {
+    pub type_name: &'static str,
+    pub value: String,
+    /// the value looks like something a program would store
+    pub likely: bool,
+}
+
+fn plausible_integer(value: i64) -> bool {
+    value != 0 && value.unsigned_abs() <= 1_000_000
+}
+
+fn plausible_float(value: f64) -> bool {
+    value.is_finite() && (1e-4..=1e6).contains(&value.abs()) && value.fract() != 0.0
+}
+
+/// reads `bytes` as every supported type, `is_mapped` tells whether a value could be a pointer
+pub fn guess_types(bytes: &[u8; 8], is_mapped: impl Fn(usize) -> bool) -> Vec<Interpretation> {
+    let read = |range: std::ops::Range<usize>| bytes[range].to_vec();
+    let i32_value = i32::read_value(read(0..4)).expect("4 bytes are enough");
+    let u32_value = u32::read_value(read(0..4)).expect("4 bytes are enough");
+    let f32_value = f32::read_value(read(0..4)).expect("4 bytes are enough");
+    let low = u16::read_value(read(0..2)).expect("2 bytes are enough");
+    let high = u16::read_value(read(2..4)).expect("2 bytes are enough");
+    let i64_value = i64::read_value(read(0..8)).expect("8 bytes are enough");
+    let f64_value = f64::read_value(read(0..8)).expect("8 bytes are enough");
+    let pointer = u64::read_value(read(0..8)).expect("8 bytes are enough") as usize;
+    vec![
+        Interpretation {
+            type_name: "i32",
+            value: i32_value.to_string(),
+            likely: plausible_integer(i32_value as i64),
+        },
+        Interpretation {
+            type_name: "u32",
+            value: u32_value.to_string(),
+            likely: plausible_integer(u32_value as i64),
+        },
+        Interpretation {
+            type_name: "f32",
+            value: f32_value.to_string(),
+            likely: plausible_float(f32_value as f64),
+        },
+        Interpretation {
+            type_name: "2x u16",
+            value: format!("{} {}", low, high),
+            likely: (low != 0 || high != 0) && low <= 10_000 && high <= 10_000,
+        },
+        Interpretation {
+            type_name: "i64",
+            value: i64_value.to_string(),
+            likely: plausible_integer(i64_value),
+        },
+        Interpretation {
+            type_name: "f64",
+            value: f64_value.to_string(),
+            likely: plausible_float(f64_value),
+        },
+        Interpretation {
+            type_name: "pointer",
+            value: format!("0x{:x}", pointer),
+            likely: pointer != 0 && is_mapped(pointer),
+        },
+    ]
+}
+
+/// parsing of values typed in by the user. `0x` hex and `0b` binary are the raw bit pattern
+/// of the type (so `0xFFFFFFFF` is -1 as an i32 and `0x3F800000` is 1.0 as an f32),
+/// decimal integers have to fit the type, except that negative ones are two's complement
+/// for unsigned types (so -1 is `0xFFFFFFFF` as a u32, but 200 is not an i8)
+pub trait FromUserInput: Sized {
+    fn from_user_input(s: &str) -> Option<Self>;
+}
+
+/// `0x` / `0b` prefixed input as the bits of an unsigned integer
+macro_rules! parse_bits {
+    ($s:expr, $Bits:ty) => {
+        match ($s.strip_prefix("0x"), $s.strip_prefix("0b")) {
+            (Some(hex), _) => Some(<$Bits>::from_str_radix(hex, 16).ok()),
+            (_, Some(binary)) => Some(<$Bits>::from_str_radix(binary, 2).ok()),
+            _ => None,
+        }
+    };
+}
+
+macro_rules! from_user_input_impl {
+    (integer: $(($SelfT:ty, $Signed:ty, $Unsigned:ty)),*) => {
+        $(
+            impl FromUserInput for $SelfT {
+                fn from_user_input(s: &str) -> Option<Self> {
+                    match parse_bits!(s, $Unsigned) {
+                        Some(bits) => bits.map(|bits| bits as Self),
+                        None => s.parse::<Self>().ok().or_else(|| {
+                            s.parse::<$Signed>()
+                                .ok()
+                                .filter(|value| *value < 0)
+                                .map(|value| value as Self)
+                        }),
+                    }
+                }
+            }
+        )*
+    };
+    (float: $(($SelfT:ty, $Bits:ty)),*) => {
+        $(
+            impl FromUserInput for $SelfT {
+                fn from_user_input(s: &str) -> Option<Self> {
+                    match parse_bits!(s, $Bits) {
+                        Some(bits) => bits.map(Self::from_bits),
+                        None => s.parse().ok(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_user_input_impl!(integer:
+    (i8, i8, u8),
+    (u8, i8, u8),
+    (i16, i16, u16),
+    (u16, i16, u16),
+    (i32, i32, u32),
+    (u32, i32, u32),
+    (i64, i64, u64),
+    (u64, i64, u64),
+    (usize, isize, usize)
+);
+from_user_input_impl!(float: (f32, u32), (f64, u64));
+
+/// hex formatting for integers, floats have no sensible hex form so they stay decimal
+pub trait DisplayHex {
+    fn display_hex(&self) -> String;
+}
+
+macro_rules! display_hex_impl {
+    (integer: $($SelfT:ty),*) => {
+        $(
+            impl DisplayHex for $SelfT {
+                fn display_hex(&self) -> String {
+                    format!("0x{:x}", self)
+                }
+            }
+        )*
+    };
+    (float: $($SelfT:ty),*) => {
+        $(
+            impl DisplayHex for $SelfT {
+                fn display_hex(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+display_hex_impl!(integer: i8, u8, i16, u16, i32, u32, i64, u64);
+display_hex_impl!(float: f32, f64);
+
+/// lossy conversion used for ratio math that doesn't fit the scanned type
+pub trait ToF64 {
+    fn to_f64(&self) -> f64;
+}
+
+macro_rules! to_f64_impl {
+    ($($SelfT:ty),*) => {
+        $(
+            impl ToF64 for $SelfT {
+                fn to_f64(&self) -> f64 {
+                    *self as f64
+                }
+            }
+        )*
+    };
+}
+
+to_f64_impl!(i8, u8, i16, u16, i32, u32, i64, u64, f32, f64);
+
+/// tells integers and floats apart for filters that only make sense for one of them
+pub trait NumberKind {
+    const IS_FLOAT: bool;
+    fn value_is_nan(&self) -> bool;
+    fn value_is_finite(&self) -> bool;
+    /// bitwise and, floats use their raw bit pattern
+    fn masked(&self, mask: Self) -> Self;
+    /// nothing is divisible by 0
+    fn divisible_by(&self, divisor: Self) -> bool;
+    /// same as `as i64`, big `u64`s wrap around and floats are truncated
+    fn to_i64(&self) -> i64;
+    /// exact for every integer, floats are truncated
+    fn to_i128(&self) -> i128;
+}
+
+macro_rules! number_kind_impl {
+    (integer: $($SelfT:ty),*) => {
+        $(
+            impl NumberKind for $SelfT {
+                const IS_FLOAT: bool = false;
+
+                fn value_is_nan(&self) -> bool {
+                    false
+                }
+
+                fn value_is_finite(&self) -> bool {
+                    true
+                }
+
+                fn masked(&self, mask: Self) -> Self {
+                    self & mask
+                }
+
+                fn divisible_by(&self, divisor: Self) -> bool {
+                    divisor != 0 && self % divisor == 0
+                }
+
+                fn to_i64(&self) -> i64 {
+                    *self as i64
+                }
+
+                fn to_i128(&self) -> i128 {
+                    *self as i128
+                }
+            }
+        )*
+    };
+    (float: $($SelfT:ty),*) => {
+        $(
+            impl NumberKind for $SelfT {
+                const IS_FLOAT: bool = true;
+
+                fn value_is_nan(&self) -> bool {
+                    self.is_nan()
+                }
+
+                fn value_is_finite(&self) -> bool {
+                    self.is_finite()
+                }
+
+                fn masked(&self, mask: Self) -> Self {
+                    Self::from_bits(self.to_bits() & mask.to_bits())
+                }
+
+                fn divisible_by(&self, divisor: Self) -> bool {
+                    divisor != 0.0 && self % divisor == 0.0
+                }
+
+                fn to_i64(&self) -> i64 {
+                    *self as i64
+                }
+
+                fn to_i128(&self) -> i128 {
+                    *self as i128
+                }
+            }
+        )*
+    };
+}
+
+number_kind_impl!(integer: i8, u8, i16, u16, i32, u32, i64, u64);
+number_kind_impl!(float: f32, f64);
+
 pub trait ReadFromBytes:
     Default
     + std::fmt::Display
     + std::fmt::Debug
     + Sized
     + FromStr
+    + FromUserInput
+    + DisplayHex
+    + ToF64
+    + NumberKind
     + Clone
     + PartialEq
     + PartialOrd
@@ -24,9 +459,11 @@ pub trait ReadFromBytes:
     + Sync
     + Send
 {
+    /// every value in `reader`, starting at every `step`th byte
     fn possible_values<'a>(
         reader: &'a [u8],
         base: usize,
+        step: usize,
     ) -> Box<dyn Iterator<Item = AddressEntry<Self>> + 'a>;
 
     fn read_value(val: Vec<u8>) -> std::io::Result<Self>;
@@ -37,16 +474,19 @@ impl ReadFromBytes for u8 {
     fn possible_values<'a>(
         memory: &'a [u8],
         base: usize,
+        step: usize,
     ) -> Box<dyn Iterator<Item = AddressEntry<Self>> + 'a> {
         Box::new(
-            (0..(memory.len() - std::mem::size_of::<Self>())).filter_map(move |start| {
-                Some((
-                    base + start,
-                    Cursor::new(&memory[start..start + std::mem::size_of::<Self>()])
-                        .read_u8()
-                        .ok()?,
-                ))
-            }),
+            (0..(memory.len() + 1).saturating_sub(std::mem::size_of::<Self>()))
+                .step_by(step)
+                .filter_map(move |start| {
+                    Some((
+                        base + start,
+                        Cursor::new(&memory[start..start + std::mem::size_of::<Self>()])
+                            .read_u8()
+                            .ok()?,
+                    ))
+                }),
         )
     }
 
@@ -61,32 +501,64 @@ impl ReadFromBytes for u8 {
     }
 }
 
+impl ReadFromBytes for i8 {
+    fn possible_values<'a>(
+        memory: &'a [u8],
+        base: usize,
+        step: usize,
+    ) -> Box<dyn Iterator<Item = AddressEntry<Self>> + 'a> {
+        Box::new(
+            (0..(memory.len() + 1).saturating_sub(std::mem::size_of::<Self>()))
+                .step_by(step)
+                .filter_map(move |start| {
+                    Some((
+                        base + start,
+                        Cursor::new(&memory[start..start + std::mem::size_of::<Self>()])
+                            .read_i8()
+                            .ok()?,
+                    ))
+                }),
+        )
+    }
+
+    fn read_value(val: Vec<u8>) -> std::io::Result<Self> {
+        let mut c = std::io::Cursor::new(val);
+        Ok(c.read_i8()?)
+    }
+
+    fn write_bytes<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_i8(*self)?;
+        Ok(())
+    }
+}
+
 macro_rules! read_from_bytes_impl {
     ($SelfT:ty, $method:ident, $write_method:ident) => {
         impl ReadFromBytes for $SelfT {
             fn possible_values<'a>(
                 memory: &'a [u8],
                 base: usize,
+                step: usize,
             ) -> Box<dyn Iterator<Item = AddressEntry<$SelfT>> + 'a> {
+                let endian = Endian::current();
                 Box::new(
-                    (0..(memory.len() - std::mem::size_of::<$SelfT>())).filter_map(move |start| {
-                        Some((
-                            base + start,
-                            Cursor::new(&memory[start..start + std::mem::size_of::<$SelfT>()])
-                                .$method::<NativeEndian>()
-                                .ok()?,
-                        ))
-                    }),
+                    (0..(memory.len() + 1).saturating_sub(std::mem::size_of::<$SelfT>()))
+                        .step_by(step)
+                        .filter_map(move |start| {
+                            let mut cursor =
+                                Cursor::new(&memory[start..start + std::mem::size_of::<$SelfT>()]);
+                            Some((base + start, with_endian!(endian, cursor, $method).ok()?))
+                        }),
                 )
             }
 
             fn read_value(val: Vec<u8>) -> std::io::Result<Self> {
                 let mut c = std::io::Cursor::new(val);
-                Ok(c.$method::<NativeEndian>()?)
+                Ok(with_endian!(Endian::current(), c, $method)?)
             }
 
             fn write_bytes<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
-                writer.$write_method::<NativeEndian>(*self)?;
+                with_endian!(Endian::current(), writer, $write_method, *self)?;
                 Ok(())
             }
         }
@@ -101,3 +573,96 @@ read_from_bytes_impl!(i16, read_i16, write_i16);
 read_from_bytes_impl!(u16, read_u16, write_u16);
 read_from_bytes_impl!(f32, read_f32, write_f32);
 read_from_bytes_impl!(f64, read_f64, write_f64);
+
+#[cfg(test)]
+mod test_from_user_input {
+    use super::*;
+
+    #[test]
+    fn test_bit_patterns() {
+        assert_eq!(i32::from_user_input("0xFFFFFFFF"), Some(-1));
+        assert_eq!(u32::from_user_input("-1"), Some(0xFFFF_FFFF));
+        assert_eq!(u8::from_user_input("0b1010"), Some(10));
+        assert_eq!(i8::from_user_input("200"), None);
+        assert_eq!(i8::from_user_input("300"), None);
+        assert_eq!(u8::from_user_input("-129"), None);
+        assert_eq!(f32::from_user_input("0x3F800000"), Some(1.0));
+        assert_eq!(f64::from_user_input("-2.5"), Some(-2.5));
+        assert_eq!(usize::from_user_input("0x7f0010"), Some(0x7f0010));
+        assert_eq!(u16::from_user_input("0x10000"), None);
+        assert_eq!(i32::from_user_input("nope"), None);
+    }
+}
+
+#[cfg(test)]
+mod test_possible_values {
+    use super::*;
+
+    #[test]
+    fn test_guess_types() {
+        let likely = |bytes: [u8; 8], is_mapped: bool| {
+            guess_types(&bytes, |_| is_mapped)
+                .into_iter()
+                .filter(|interpretation| interpretation.likely)
+                .map(|interpretation| interpretation.type_name)
+                .collect::<Vec<_>>()
+        };
+        let mut health = [0u8; 8];
+        health[..4].copy_from_slice(&2137i32.to_ne_bytes());
+        assert!(likely(health, false).contains(&"i32"));
+        assert!(!likely(health, false).contains(&"f32"));
+
+        let mut speed = [0u8; 8];
+        speed[..4].copy_from_slice(&1.5f32.to_ne_bytes());
+        assert!(likely(speed, false).contains(&"f32"));
+        assert!(!likely(speed, false).contains(&"i32"));
+
+        let pointer = 0x7f00_1234_5678usize.to_ne_bytes();
+        assert!(likely(pointer, true).contains(&"pointer"));
+        assert!(!likely(pointer, false).contains(&"pointer"));
+
+        // its absolute value doesn't fit an i64
+        assert!(!likely(i64::MIN.to_ne_bytes(), false).contains(&"i64"));
+    }
+
+    #[test]
+    fn test_value_at_the_end_is_found() {
+        let mut memory = vec![0u8; 12];
+        memory[8..].copy_from_slice(&2137i32.to_ne_bytes());
+        let values = i32::possible_values(&memory, 0x1000, 4).collect::<Vec<_>>();
+        assert_eq!(values.len(), 3);
+        assert_eq!(values.last(), Some(&(0x1008, 2137)));
+
+        let values = u8::possible_values(&[1, 2, 3], 0, 1).collect::<Vec<_>>();
+        assert_eq!(values, vec![(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_big_endian() {
+        // the global byte order isn't touched here, tests run in parallel
+        let mut cursor = Cursor::new(vec![0, 0, 0x08, 0x59]);
+        assert_eq!(with_endian!(Endian::Big, cursor, read_u32).unwrap(), 2137);
+        let mut written = vec![];
+        with_endian!(Endian::Big, written, write_u32, 2137).unwrap();
+        assert_eq!(written, vec![0, 0, 0x08, 0x59]);
+        assert_eq!("big".parse::<Endian>().unwrap(), Endian::Big);
+    }
+
+    #[test]
+    fn test_find_triplets() {
+        let memory = [0.0f32, 1.0, 2.5, -7.0, 10.0, 0.0]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            find_triplets(&memory, 0x1000, 4, &[2.5f32, -7.0, 10.1], 0.2),
+            vec![(0x1008, 2.5)]
+        );
+        assert!(find_triplets(&memory, 0x1000, 4, &[2.5f32, -7.0, 11.0], 0.2).is_empty());
+    }
+
+    #[test]
+    fn test_memory_smaller_than_value() {
+        assert_eq!(u64::possible_values(&[1, 2, 3], 0, 1).count(), 0);
+    }
+}
@@ -1,9 +1,19 @@
-use crate::{AddressValue, ProcessQuery, error::{BetrayalError, BetrayalResult}, memory::ReadFromBytes};
+use crate::{
+    error::{BetrayalError, BetrayalResult},
+    memory::ReadFromBytes,
+    AddressValue, ProcessQuery,
+};
+use itertools::Itertools;
+use parking_lot::Mutex;
+use rayon::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct NeighbourValuesQuery<T: ReadFromBytes> {
     pub window_size: usize,
     pub values: Vec<T>,
+    /// values within this distance of a wanted one count as found (float modes only),
+    /// `values` stays a plain `Vec` so a lookup is a linear scan either way
+    pub epsilon: Option<T>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -11,3 +21,189 @@ pub struct NeighbourValues<T: ReadFromBytes> {
     pub window_size: usize,
     pub values: Vec<AddressValue<T>>,
 }
+
+impl<T: ReadFromBytes> NeighbourValuesQuery<T> {
+    fn matches(&self, value: T, wanted: T) -> bool {
+        match self.epsilon {
+            Some(epsilon) => crate::memory::approximately_equal(value, wanted, epsilon),
+            None => value == wanted,
+        }
+    }
+
+    /// every window starting at one of the wanted values that contains all of them (in any order),
+    /// `entries` have to be sorted by address
+    pub fn find_in(&self, entries: &[AddressValue<T>]) -> Vec<NeighbourValues<T>> {
+        let candidates = entries
+            .iter()
+            .filter(|(_info, _address, value)| {
+                self.values
+                    .iter()
+                    .any(|wanted| self.matches(*value, *wanted))
+            })
+            .collect::<Vec<_>>();
+        candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(start, (_info, first_address, _value))| {
+                let window = candidates[start..]
+                    .iter()
+                    .take_while(|(_info, address, _value)| {
+                        *address < first_address + self.window_size
+                    })
+                    .collect::<Vec<_>>();
+                // every wanted value needs its own address, values can repeat
+                let mut used = vec![false; window.len()];
+                let values = self
+                    .values
+                    .iter()
+                    .map(|wanted| {
+                        let (index, found) = window.iter().enumerate().find(
+                            |(index, (_info, _address, value))| {
+                                !used[*index] && self.matches(*value, *wanted)
+                            },
+                        )?;
+                        used[index] = true;
+                        Some(***found)
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                // windows that don't use their first value are found again from a later start
+                used.first()
+                    .copied()
+                    .unwrap_or(false)
+                    .then(|| NeighbourValues {
+                        window_size: self.window_size,
+                        values: values
+                            .into_iter()
+                            .sorted_by_key(|(_info, address, _value)| *address)
+                            .collect(),
+                    })
+            })
+            .collect()
+    }
+}
+
+impl<T: ReadFromBytes> ProcessQuery<T> {
+    /// scans memory for places where all of the `query` values are at most `window_size` bytes apart
+    pub fn find_neighbour_values(
+        &mut self,
+        query: NeighbourValuesQuery<T>,
+    ) -> BetrayalResult<Vec<NeighbourValues<T>>> {
+        if query.values.is_empty() || query.window_size == 0 {
+            return Err(BetrayalError::BadCommand(
+                "neighbour values need a window size and at least one value".to_string(),
+            ));
+        }
+        self.update_mappings()?;
+        let pid = self.pid;
+        let step = self.alignment.step::<T>();
+        let mappings: Vec<_> = self
+            .mappings
+            .iter()
+            .filter(|(info, map)| info.writable && self.in_scanned_region(map))
+            .unique_by(|(_info, m)| m.base)
+            .collect();
+
+        let found: Mutex<Vec<NeighbourValues<T>>> = Default::default();
+        // a window starting near the end of a chunk reaches into the next one
+        let overlap = query.window_size + std::mem::size_of::<T>() - 1;
+        mappings.into_par_iter().for_each(|(info, map)| {
+            for (start, chunk_end, memory) in
+                crate::read_chunks(pid, map.base, map.ceiling, overlap)
+            {
+                let entries = match memory {
+                    Ok(memory) => T::possible_values(&memory[..], start, step)
+                        .map(|(address, value)| (*info, address, value))
+                        .collect::<Vec<_>>(),
+                    Err(_e) => return,
+                };
+                // windows starting in the overlap are found again by the next chunk
+                let mut in_chunk = query
+                    .find_in(&entries)
+                    .into_iter()
+                    .filter(|neighbours| {
+                        neighbours
+                            .values
+                            .first()
+                            .map(|(_info, address, _value)| *address < chunk_end)
+                            .unwrap_or(false)
+                    })
+                    .collect();
+                found.lock().append(&mut in_chunk);
+            }
+        });
+        let mut found = found.into_inner();
+        found.sort_by_key(|neighbours| {
+            neighbours
+                .values
+                .first()
+                .map(|(_info, address, _value)| *address)
+        });
+        Ok(found)
+    }
+}
+
+#[cfg(test)]
+mod test_neighbour_values {
+    use super::*;
+    use crate::AddressInfo;
+
+    fn entries(values: &[(usize, i32)]) -> Vec<AddressValue<i32>> {
+        values
+            .iter()
+            .map(|(address, value)| (AddressInfo { writable: true }, *address, *value))
+            .collect()
+    }
+
+    #[test]
+    fn test_values_in_window() {
+        let query = NeighbourValuesQuery {
+            window_size: 12,
+            values: vec![14, 1, 2],
+            epsilon: None,
+        };
+        let memory = entries(&[(0, 1), (4, 2), (8, 14), (12, 1), (32, 2), (36, 14)]);
+        let found = query.find_in(&memory);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            found[0]
+                .values
+                .iter()
+                .map(|(_info, address, _value)| *address)
+                .collect::<Vec<_>>(),
+            vec![0, 4, 8]
+        );
+    }
+
+    #[test]
+    fn test_repeated_values_need_separate_addresses() {
+        let query = NeighbourValuesQuery {
+            window_size: 16,
+            values: vec![5, 5],
+            epsilon: None,
+        };
+        assert!(query.find_in(&entries(&[(0, 5), (64, 5)])).is_empty());
+        assert_eq!(query.find_in(&entries(&[(0, 5), (8, 5)])).len(), 1);
+    }
+
+    #[test]
+    fn test_values_within_epsilon() {
+        let memory = [(0, 1.49f32), (4, 20.02), (64, 1.5), (68, 21.0)]
+            .iter()
+            .map(|(address, value)| (AddressInfo { writable: true }, *address, *value))
+            .collect::<Vec<_>>();
+        let query = NeighbourValuesQuery {
+            window_size: 8,
+            values: vec![1.5, 20.0],
+            epsilon: Some(0.1),
+        };
+        let found = query.find_in(&memory);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].values[0].1, 0);
+        assert!(NeighbourValuesQuery {
+            epsilon: None,
+            ..query
+        }
+        .find_in(&memory)
+        .is_empty());
+    }
+}
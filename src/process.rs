@@ -1,2 +1,30 @@
+use crate::error::{BetrayalError, BetrayalResult};
 
+/// finds the PID of a process whose name contains `name`, fails if there's more than one candidate
+pub fn resolve_pid_by_name(name: &str) -> BetrayalResult<i32> {
+    let own_pid = std::process::id() as i32;
+    let candidates = procfs::process::all_processes()?
+        .into_iter()
+        .filter(|process| process.pid != own_pid && process.stat.comm.contains(name))
+        .collect::<Vec<_>>();
+    match &candidates[..] {
+        [] => Err(BetrayalError::BadPid),
+        [process] => Ok(process.pid),
+        candidates => {
+            println!(" :: more than one process matches [{}]:", name);
+            for process in candidates {
+                println!("{:>8} :: {}", process.pid, process.stat.comm);
+            }
+            Err(BetrayalError::BadCommand(format!(
+                "{} processes match [{}], pass --pid instead",
+                candidates.len(),
+                name
+            )))
+        }
+    }
+}
 
+/// the process is gone once its `/proc` entry is
+pub fn has_exited(pid: i32) -> bool {
+    !std::path::Path::new(&format!("/proc/{}", pid)).exists()
+}
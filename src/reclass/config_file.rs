@@ -1,4 +1,8 @@
-use crate::{error::BetrayalResult, memory::ReadFromBytes, AddressInfo, ProcessQuery};
+use crate::{
+    error::{BetrayalError, BetrayalResult},
+    memory::ReadFromBytes,
+    AddressInfo, ProcessQuery,
+};
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashSet, convert::TryInto};
@@ -7,12 +11,81 @@ pub fn read_memory<T: ReadFromBytes>(pid: i32, address: usize) -> BetrayalResult
     ProcessQuery::<T>::new(pid)
         .read_at(pid, address)
         .map(|(info, _address, value)| (info, value))
+        .map_err(|e| explain_read_error(pid, address, std::mem::size_of::<T>(), e))
+}
+
+pub fn read_bytes(pid: i32, address: usize, len: usize) -> BetrayalResult<(AddressInfo, Vec<u8>)> {
+    let read = || -> BetrayalResult<_> {
+        let mut process = ProcessQuery::<u8>::new(pid);
+        process.update_mappings()?;
+        let info = AddressInfo::from_address(&process, pid, address)?;
+        Ok((info, crate::read_memory(pid, address, len)?))
+    };
+    read().map_err(|e| explain_read_error(pid, address, len, e))
+}
+
+/// tells a bad address apart from a field running off the end of its mapping
+fn explain_read_error(pid: i32, address: usize, len: usize, error: BetrayalError) -> BetrayalError {
+    let mappings = match ProcessQuery::<u8>::mappings_all(pid) {
+        Ok(mappings) => mappings,
+        Err(_) => return error,
+    };
+    let mapped = |address: usize| {
+        mappings
+            .iter()
+            .any(|(_info, map)| map.base <= address && address < map.ceiling)
+    };
+    if !mapped(address) {
+        BetrayalError::Unmapped(address)
+    } else if !mapped(address.saturating_add(len.saturating_sub(1))) {
+        BetrayalError::ReadPastMapping(address, len)
+    } else {
+        error
+    }
+}
+
+fn is_mapped(pid: i32, address: usize) -> BetrayalResult<bool> {
+    Ok(ProcessQuery::<u8>::mappings_all(pid)?
+        .into_iter()
+        .any(|(_info, map)| map.base <= address && address < map.ceiling))
+}
+
+/// reads a pointer of type `P` at `address` and the field it points to,
+/// `wrap` turns a failed read into the right result variant
+fn follow_pointer<P: ReadFromBytes + TryInto<usize>>(
+    field: Field,
+    pid: i32,
+    address: usize,
+    wrap: fn(ValueResult<P>) -> FieldResult,
+) -> FieldResult {
+    match read_memory::<P>(pid, address) {
+        Ok((_info, target)) => match target.try_into() {
+            Ok(target) => dereference(field, pid, target),
+            Err(_) => wrap(ValueResult::Err(format!(
+                "error :: {} doesn't fit in a pointer on this platform",
+                target
+            ))),
+        },
+        Err(e) => wrap(Err(e).into()),
+    }
+}
+
+/// null and unmapped targets are reported as such instead of failing the read
+fn dereference(field: Field, pid: i32, target: usize) -> FieldResult {
+    match target {
+        0 => FieldResult::NullPointer,
+        target if !is_mapped(pid, target).unwrap_or(true) => FieldResult::UnmappedPointer(target),
+        target => field.result(pid, target),
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Field {
     Padding(usize),
+    I8,
     U8,
+    /// one byte, anything but 0 is true
+    Bool,
     I16,
     U16,
     I32,
@@ -21,10 +94,62 @@ pub enum Field {
     U64,
     F32,
     F64,
+    /// raw buffer of that many bytes
+    Bytes(usize),
+    /// null terminated string of at most that many bytes
+    CString(usize),
+    /// null terminated UTF-16 string of at most that many code units (`wchar_t` on windows)
+    WString(usize),
+    /// that many bytes of x86-64 machine code, displayed disassembled
+    Code(usize),
     Pointer32(Box<Self>),
     Pointer64(Box<Self>),
+    /// pointer as wide as the platform's (`Pointer64` on x86-64)
+    PointerNative(Box<Self>),
     Struct(ReclassStruct),
-    SearchValues(Vec<(Field, String)>),
+    SearchValues(SearchValues),
+    /// that many copies of the field laid out one after another
+    Array(Box<Self>, usize),
+    /// integer field rendered as `0x1F` instead of decimal, e.g. `flags: {Hex: U32}`
+    Hex(Box<Self>),
+    /// all of the fields read at the same address, e.g. `{Union: [I32, F32]}`
+    Union(Vec<Field>),
+}
+
+fn default_search_range() -> (usize, usize) {
+    (0, 1000)
+}
+
+/// fields to look for and the offsets to look at, either
+/// `SearchValues: {fields: [[U32, "5"]], range: [0, 4096]}` or just `SearchValues: [[U32, "5"]]`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(from = "SearchValuesDefinition")]
+pub struct SearchValues {
+    pub fields: Vec<(Field, String)>,
+    pub range: (usize, usize),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SearchValuesDefinition {
+    Fields(Vec<(Field, String)>),
+    WithRange {
+        fields: Vec<(Field, String)>,
+        #[serde(default = "default_search_range")]
+        range: (usize, usize),
+    },
+}
+
+impl From<SearchValuesDefinition> for SearchValues {
+    fn from(definition: SearchValuesDefinition) -> Self {
+        match definition {
+            SearchValuesDefinition::Fields(fields) => Self {
+                fields,
+                range: default_search_range(),
+            },
+            SearchValuesDefinition::WithRange { fields, range } => Self { fields, range },
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -63,11 +188,27 @@ pub enum FieldResult {
     U64(ValueResult<u64>),
     I64(ValueResult<i64>),
     U8(ValueResult<u8>),
+    Bool(ValueResult<bool>),
+    I8(ValueResult<i8>),
     F32(ValueResult<f32>),
     F64(ValueResult<f64>),
+    Bytes(ValueResult<Vec<u8>>),
+    CString(ValueResult<String>),
+    WString(ValueResult<String>),
+    /// address the code was read from and its bytes
+    Code(usize, ValueResult<Vec<u8>>),
     Pointer32(usize, Box<Self>),
     Pointer64(usize, Box<Self>),
     ReclassStruct(ReclassResult),
+    Array(Vec<FieldResult>),
+    Hex(Box<Self>),
+    Union(Vec<FieldResult>),
+    /// `SearchValues` hit and the offset it was found at
+    Found(usize, Box<Self>),
+    /// pointer field holding 0
+    NullPointer,
+    /// pointer field holding an address outside of any mapping
+    UnmappedPointer(usize),
 }
 
 impl FieldResult {
@@ -81,8 +222,14 @@ impl FieldResult {
             FieldResult::I32(r) => r.info(),
             FieldResult::I16(r) => r.info(),
             FieldResult::U8(r) => r.info(),
+            FieldResult::Bool(r) => r.info(),
+            FieldResult::I8(r) => r.info(),
             FieldResult::F32(r) => r.info(),
             FieldResult::F64(r) => r.info(),
+            FieldResult::Bytes(r) => r.info(),
+            FieldResult::Code(_, r) => r.info(),
+            FieldResult::CString(r) => r.info(),
+            FieldResult::WString(r) => r.info(),
             FieldResult::Pointer32(_, p) => p.info(),
             FieldResult::Pointer64(_, p) => p.info(),
             FieldResult::ReclassStruct(r) => r
@@ -92,6 +239,12 @@ impl FieldResult {
                 .next()
                 .map(|s| s.info())
                 .flatten(),
+            FieldResult::Array(results) => results.first().and_then(|r| r.info()),
+            FieldResult::Hex(r) => r.info(),
+            FieldResult::Union(results) => results.iter().find_map(|r| r.info()),
+            FieldResult::Found(_, r) => r.info(),
+            FieldResult::NullPointer => None,
+            FieldResult::UnmappedPointer(_) => None,
         }
     }
 }
@@ -103,23 +256,47 @@ impl Field {
             Field::I32 => std::mem::size_of::<i32>(),
             Field::I16 => std::mem::size_of::<i16>(),
             Field::U8 => std::mem::size_of::<u8>(),
+            Field::Bool => std::mem::size_of::<u8>(),
+            Field::I8 => std::mem::size_of::<i8>(),
             Field::F32 => std::mem::size_of::<f32>(),
             Field::F64 => std::mem::size_of::<f64>(),
+            Field::Bytes(len) => *len,
+            Field::Code(len) => *len,
+            Field::CString(max_len) => *max_len,
+            Field::WString(max_len) => *max_len * std::mem::size_of::<u16>(),
             Field::Pointer32(_) => std::mem::size_of::<u32>(),
             Field::Pointer64(_) => std::mem::size_of::<u64>(),
+            Field::PointerNative(_) => std::mem::size_of::<usize>(),
             Field::Struct(_) => 0,
             Field::U16 => std::mem::size_of::<u16>(),
             Field::U32 => std::mem::size_of::<u32>(),
             Field::I64 => std::mem::size_of::<i64>(),
             Field::U64 => std::mem::size_of::<u64>(),
-            Field::SearchValues(v) => 0,
+            Field::SearchValues(_) => 0,
+            Field::Array(field, count) => field.stride() * count,
+            Field::Hex(field) => field.size(),
+            Field::Union(fields) => fields.iter().map(|field| field.size()).max().unwrap_or(0),
+        }
+    }
+
+    /// distance between consecutive array elements, unlike `size` it counts the whole struct
+    pub fn stride(&self) -> usize {
+        match self {
+            Field::Struct(reclass_struct) => reclass_struct.size(),
+            field => field.size(),
         }
     }
 
     pub fn result(self, pid: i32, address: usize) -> FieldResult {
         match self {
             Field::Padding(s) => FieldResult::Padding(s),
+            Field::I8 => FieldResult::I8(read_memory::<i8>(pid, address).into()),
             Field::U8 => FieldResult::U8(read_memory::<u8>(pid, address).into()),
+            Field::Bool => FieldResult::Bool(
+                read_memory::<u8>(pid, address)
+                    .map(|(info, value)| (info, value != 0))
+                    .into(),
+            ),
             Field::I16 => FieldResult::I16(read_memory::<i16>(pid, address).into()),
             Field::U16 => FieldResult::U16(read_memory::<u16>(pid, address).into()),
             Field::I32 => FieldResult::I32(read_memory::<i32>(pid, address).into()),
@@ -128,32 +305,50 @@ impl Field {
             Field::U64 => FieldResult::U64(read_memory::<u64>(pid, address).into()),
             Field::F32 => FieldResult::F32(read_memory::<f32>(pid, address).into()),
             Field::F64 => FieldResult::F64(read_memory::<f64>(pid, address).into()),
+            Field::Bytes(len) => FieldResult::Bytes(read_bytes(pid, address, len).into()),
+            Field::Code(len) => FieldResult::Code(address, read_bytes(pid, address, len).into()),
+            Field::CString(max_len) => FieldResult::CString(
+                read_bytes(pid, address, max_len)
+                    .map(|(info, bytes)| {
+                        let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+                        (info, String::from_utf8_lossy(&bytes[..len]).into_owned())
+                    })
+                    .into(),
+            ),
+            Field::WString(max_len) => FieldResult::WString(
+                read_bytes(pid, address, max_len * std::mem::size_of::<u16>())
+                    .map(|(info, bytes)| (info, decode_utf16(&bytes)))
+                    .into(),
+            ),
             Field::Pointer32(field) => FieldResult::Pointer32(
                 address,
-                match read_memory::<u32>(pid, address) {
-                    Ok((_info, address)) => {
-                        Box::new(field.result(pid, address.try_into().expect("bad platform")))
-                    }
-                    Err(e) => Box::new(FieldResult::U32(Err(e).into())),
-                },
+                Box::new(follow_pointer::<u32>(
+                    *field,
+                    pid,
+                    address,
+                    FieldResult::U32,
+                )),
             ),
             Field::Pointer64(field) => FieldResult::Pointer64(
                 address,
-                match read_memory::<u64>(pid, address) {
-                    Ok((_info, address)) => {
-                        Box::new(field.result(pid, address.try_into().expect("bad platform")))
-                    }
-                    Err(e) => Box::new(FieldResult::U64(Err(e).into())),
-                },
+                Box::new(follow_pointer::<u64>(
+                    *field,
+                    pid,
+                    address,
+                    FieldResult::U64,
+                )),
             ),
+            Field::PointerNative(field) => match std::mem::size_of::<usize>() {
+                4 => Field::Pointer32(field).result(pid, address),
+                _ => Field::Pointer64(field).result(pid, address),
+            },
             Field::Struct(reclass_struct) => {
                 FieldResult::ReclassStruct(reclass_struct.result(pid, address))
             }
-            Field::SearchValues(fields) => {
-                let mut fields = fields.clone();
+            Field::SearchValues(SearchValues { fields, range }) => {
                 let mut last_result = FieldResult::Padding(0);
                 println!(" --- searching ");
-                for offset in 0..1000usize {
+                for offset in range.0..range.1 {
                     print!(".");
                     let search_address = address + offset;
                     for (_field_idx, (field, value)) in fields.iter().enumerate().rev() {
@@ -161,7 +356,7 @@ impl Field {
                         match result.compare_value() {
                             Some(v) if &v == value => {
                                 println!("\n\nfound! addres: {address} + Padding({offset})\n");
-                                return result.into();
+                                return FieldResult::Found(offset, Box::new(result));
                             }
                             _ => {
                                 last_result = result;
@@ -171,6 +366,21 @@ impl Field {
                 }
                 last_result.into()
             }
+            Field::Array(field, count) => {
+                let stride = field.stride();
+                FieldResult::Array(
+                    (0..count)
+                        .map(|index| field.as_ref().clone().result(pid, address + index * stride))
+                        .collect(),
+                )
+            }
+            Field::Hex(field) => FieldResult::Hex(Box::new(field.result(pid, address))),
+            Field::Union(fields) => FieldResult::Union(
+                fields
+                    .into_iter()
+                    .map(|field| field.result(pid, address))
+                    .collect(),
+            ),
         }
     }
 }
@@ -185,12 +395,25 @@ impl FieldResult {
             FieldResult::U64(v) => v.compare_value(),
             FieldResult::I64(v) => v.compare_value(),
             FieldResult::U8(v) => v.compare_value(),
+            FieldResult::Bool(v) => v.compare_value(),
+            FieldResult::I8(v) => v.compare_value(),
             FieldResult::F32(v) => v.compare_value(),
             FieldResult::F64(v) => v.compare_value(),
+            FieldResult::Bytes(ValueResult::Ok(_, bytes)) => Some(super::display::hex_bytes(bytes)),
+            FieldResult::Bytes(_) => None,
+            FieldResult::Code(..) => None,
+            FieldResult::CString(v) => v.compare_value(),
+            FieldResult::WString(v) => v.compare_value(),
             FieldResult::Pointer32(v, _) => Some(v.to_string()),
             FieldResult::Pointer64(v, _) => Some(v.to_string()),
             FieldResult::ReclassStruct(_) => None,
             FieldResult::Padding(_) => None,
+            FieldResult::Array(_) => None,
+            FieldResult::Union(_) => None,
+            FieldResult::Hex(v) => v.compare_value(),
+            FieldResult::Found(_, v) => v.compare_value(),
+            FieldResult::NullPointer => None,
+            FieldResult::UnmappedPointer(_) => None,
         }
     }
 }
@@ -212,26 +435,32 @@ pub struct ReclassStruct {
 }
 
 impl ReclassStruct {
+    pub fn size(&self) -> usize {
+        self.fields.values().map(|field| field.size()).sum()
+    }
+
     pub fn result(self, pid: i32, address: usize) -> ReclassResult {
-        let mut base = address;
+        let mut offset = 0;
         let mut fields = vec![];
         for (name, field) in self.fields {
             let size = field.size();
-            fields.push((name, base, field));
-            base += size;
+            fields.push((name, offset, field));
+            offset += size;
         }
         ReclassResult {
             name: self.name,
             fields: fields
                 .into_iter()
-                .map(|(name, address, field)| {
-                    let result = field.result(pid, address);
+                .map(|(name, offset, field)| {
+                    let field_address = address + offset;
+                    let result = field.result(pid, field_address);
                     let is_static = result.info().map(|i| i.is_static()).unwrap_or(false);
                     (
                         format!(
-                            "[{}{}] :: {}",
+                            "[+0x{:<4x} {}{}] :: {}",
+                            offset,
                             if is_static { "@" } else { "" },
-                            address,
+                            field_address,
                             name
                         ),
                         result,
@@ -266,6 +495,12 @@ impl Default for ConfigEntry {
 impl ConfigEntry {
     pub fn result(self, pid: i32) -> BetrayalResult<ConfigEntryResult> {
         let base_address = super::scripting::calculate_address(pid, &self.base_address)?;
+        if !is_mapped(pid, base_address)? {
+            return Err(BetrayalError::ConfigFileError(format!(
+                "base address 0x{:x} is not mapped",
+                base_address
+            )));
+        }
         Ok(ConfigEntryResult {
             base_address,
             struct_definition: self.struct_definition.result(pid, base_address),
@@ -335,3 +570,107 @@ impl Default for ReclassStruct {
         }
     }
 }
+
+/// little endian UTF-16 up to the first NUL code unit, unpaired surrogates become U+FFFD
+fn decode_utf16(bytes: &[u8]) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect::<Vec<_>>();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod test_config_file {
+    use super::*;
+
+    #[test]
+    fn test_float_fields_round_trip() {
+        let definition: ReclassStruct = serde_yaml::from_str(
+            r#"
+name: Position
+fields:
+  x: F32
+  y: F64
+"#,
+        )
+        .unwrap();
+        let definition: ReclassStruct =
+            serde_yaml::from_str(&serde_yaml::to_string(&definition).unwrap()).unwrap();
+        assert!(matches!(definition.fields["x"], Field::F32));
+        assert!(matches!(definition.fields["y"], Field::F64));
+        assert_eq!(definition.fields["x"].size(), 4);
+        assert_eq!(definition.fields["y"].size(), 8);
+    }
+
+    #[test]
+    fn test_wstring_field() {
+        assert_eq!(Field::WString(16).size(), 32);
+        let bytes = "hp\0junk"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(decode_utf16(&bytes), "hp");
+        assert_eq!(decode_utf16(&[0x7a, 0x00, 0x7c]), "z");
+    }
+
+    #[test]
+    fn test_hex_field_round_trip() {
+        let definition: ReclassStruct = serde_yaml::from_str(
+            r#"
+name: Entity
+fields:
+  flags:
+    Hex: U32
+"#,
+        )
+        .unwrap();
+        let definition: ReclassStruct =
+            serde_yaml::from_str(&serde_yaml::to_string(&definition).unwrap()).unwrap();
+        assert!(
+            matches!(&definition.fields["flags"], Field::Hex(field) if matches!(field.as_ref(), Field::U32))
+        );
+        assert_eq!(definition.fields["flags"].size(), 4);
+    }
+
+    #[test]
+    fn test_union_size_is_largest_member() {
+        let definition: ReclassStruct = serde_yaml::from_str(
+            r#"
+name: Entity
+fields:
+  value:
+    Union: [I32, F32, U64, U8]
+"#,
+        )
+        .unwrap();
+        assert_eq!(definition.fields["value"].size(), 8);
+        assert_eq!(definition.size(), 8);
+    }
+
+    #[test]
+    fn test_search_values_range() {
+        let definition: ReclassStruct = serde_yaml::from_str(
+            r#"
+name: Entity
+fields:
+  old:
+    SearchValues: [[U32, "5"]]
+  bounded:
+    SearchValues:
+      fields: [[U32, "5"]]
+      range: [16, 4096]
+"#,
+        )
+        .unwrap();
+        let definition: ReclassStruct =
+            serde_yaml::from_str(&serde_yaml::to_string(&definition).unwrap()).unwrap();
+        assert!(
+            matches!(&definition.fields["old"], Field::SearchValues(search) if search.range == (0, 1000))
+        );
+        assert!(
+            matches!(&definition.fields["bounded"], Field::SearchValues(search) if search.range == (16, 4096))
+        );
+    }
+}
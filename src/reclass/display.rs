@@ -1,5 +1,6 @@
-use std::fmt::{Display, Write};
+use std::fmt::{Display, UpperHex, Write};
 
+use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction, IntelFormatter};
 use indexmap::IndexMap;
 
 use super::config_file::{
@@ -19,6 +20,50 @@ fn indent(indent_level: usize) -> String {
         .collect::<String>()
 }
 
+/// `48 65 6C 6C 6F`
+pub fn hex_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `48 65 6C 6C 6F  "Hello"`, unprintable characters are shown as dots
+fn hex_ascii_bytes(bytes: &[u8]) -> String {
+    let ascii = bytes
+        .iter()
+        .map(|b| match *b {
+            0x20..=0x7E => *b as char,
+            _ => '.',
+        })
+        .collect::<String>();
+    format!("{}  \"{}\"", hex_bytes(bytes), ascii)
+}
+
+/// `0x401000: mov rax,[rbp-8]`, one instruction per line
+pub fn disassemble(address: usize, bytes: &[u8]) -> Vec<String> {
+    let mut decoder = Decoder::with_ip(64, bytes, address as u64, DecoderOptions::NONE);
+    let mut formatter = IntelFormatter::new();
+    let mut instruction = Instruction::default();
+    let mut lines = vec![];
+    while decoder.can_decode() {
+        decoder.decode_out(&mut instruction);
+        let mut line = format!("0x{:x}: ", instruction.ip());
+        formatter.format(&instruction, &mut line);
+        lines.push(line);
+    }
+    lines
+}
+
+/// like `print`, but the value is rendered as `0x1F`
+fn print_hex<T: Display + UpperHex>(value: &ValueResult<T>) -> String {
+    match value {
+        ValueResult::Ok(_, val) => format!("0x{:X}", val),
+        v => v.print(0),
+    }
+}
+
 impl Printable for ReclassResult {
     fn print(&self, indent_level: usize) -> String {
         let mut s = String::new();
@@ -63,11 +108,72 @@ impl Printable for &FieldResult {
             FieldResult::U64(v) => format!("(U64) {:<19}", v.print(0)),
             FieldResult::I64(v) => format!("(I64) {:<19}", v.print(0)),
             FieldResult::U8(v) => format!("( U8) {:<19} ", v.print(0)),
+            FieldResult::Bool(v) => format!("(BOOL) {:<19}", v.print(0)),
+            FieldResult::I8(v) => format!("( I8) {:<19} ", v.print(0)),
             FieldResult::F32(v) => format!("(F32) {:<19}", v.print(0)),
             FieldResult::F64(v) => format!("(F64) {:<19}", v.print(0)),
+            FieldResult::Bytes(v) => match v {
+                ValueResult::Ok(_, bytes) => format!("(BYTES) {}", hex_ascii_bytes(bytes)),
+                ValueResult::Err(e) => format!("(BYTES) <ERR: {}>", e),
+                ValueResult::Padding(_) => String::from("~"),
+            },
+            FieldResult::CString(v) => match v {
+                ValueResult::Ok(_, string) => format!("(STR) {:?}", string),
+                v => format!("(STR) {}", v.print(0)),
+            },
+            FieldResult::WString(v) => match v {
+                ValueResult::Ok(_, string) => format!("(WSTR) {:?}", string),
+                v => format!("(WSTR) {}", v.print(0)),
+            },
+            FieldResult::Code(address, v) => match v {
+                ValueResult::Ok(_, bytes) => std::iter::once(String::from("(CODE)"))
+                    .chain(
+                        disassemble(*address, bytes)
+                            .into_iter()
+                            .map(|line| format!("{}{}", indent(indent_level + 1), line)),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                v => format!("(CODE) {}", v.print(0)),
+            },
             FieldResult::Pointer32(addr, v) => format!("(*{addr}) {:<19}", v.as_ref().print(0)),
             FieldResult::Pointer64(addr, v) => format!("(*{addr}) {:<19}", v.as_ref().print(0)),
             FieldResult::ReclassStruct(s) => s.print(0),
+            FieldResult::Array(results) => std::iter::once(String::from("(ARRAY)"))
+                .chain(results.iter().enumerate().map(|(index, result)| {
+                    format!(
+                        "{}[{}] {}",
+                        indent(indent_level + 1),
+                        index,
+                        result.print(0)
+                    )
+                }))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            FieldResult::Union(results) => std::iter::once(String::from("(UNION)"))
+                .chain(
+                    results
+                        .iter()
+                        .map(|result| format!("{}{}", indent(indent_level + 1), result.print(0))),
+                )
+                .collect::<Vec<_>>()
+                .join("\n"),
+            FieldResult::Hex(v) => match v.as_ref() {
+                FieldResult::U16(v) => format!("(U16) {:<19}", print_hex(v)),
+                FieldResult::I16(v) => format!("(I16) {:<19}", print_hex(v)),
+                FieldResult::U32(v) => format!("(U32) {:<19}", print_hex(v)),
+                FieldResult::I32(v) => format!("(I32) {:<19}", print_hex(v)),
+                FieldResult::U64(v) => format!("(U64) {:<19}", print_hex(v)),
+                FieldResult::I64(v) => format!("(I64) {:<19}", print_hex(v)),
+                FieldResult::U8(v) => format!("( U8) {:<19} ", print_hex(v)),
+                FieldResult::I8(v) => format!("( I8) {:<19} ", print_hex(v)),
+                v => v.print(0),
+            },
+            FieldResult::Found(offset, v) => {
+                format!("{} (found at +{})", v.as_ref().print(0), offset)
+            }
+            FieldResult::NullPointer => String::from("<null>"),
+            FieldResult::UnmappedPointer(target) => format!("<unmapped 0x{:x}>", target),
         };
         format!("{}{}", indent(indent_level), s)
     }
@@ -8,35 +8,79 @@ use crate::{
 use notify::{raw_watcher, watcher, DebouncedEvent, RawEvent, RecursiveMode, Watcher};
 use serde_yaml::{from_str, to_string};
 use std::os::unix::fs::PermissionsExt;
-use std::sync::mpsc::channel;
+use std::sync::mpsc::{channel, RecvTimeoutError};
 use std::{
     fs::{read_to_string, Permissions},
     io::{BufWriter, Write},
     path::PathBuf,
     process::Command,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-pub fn run(pid: i32) -> BetrayalResult<()> {
+fn print_config(pid: i32, path: &PathBuf) -> BetrayalResult<()> {
+    let config = read_to_string(path).map_err(|e| {
+        BetrayalError::ConfigFileError(format!("failed to read config file :: {}", e))
+    })?;
+    match from_str::<Config>(&config) {
+        Ok(c) => {
+            let result = c.result(pid);
+            match result {
+                Ok(result) => println!("{}", result.print(0)),
+                Err(e) => {
+                    eprintln!("ERROR: \n {}", e.to_string())
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("bad format :: {}", e)
+        }
+    }
+    Ok(())
+}
+
+/// watches `config_path` (created with the default config if it's missing),
+/// or a throwaway temporary file when there is none.
+/// the output is also re-rendered every `refresh` so that it follows the target's values
+pub fn run(
+    pid: i32,
+    config_path: Option<PathBuf>,
+    refresh: Option<Duration>,
+) -> BetrayalResult<()> {
     println!("running reclass");
-    let mut tempfile = tempfile::Builder::new()
-        .suffix(".yaml")
-        .tempfile()
-        .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
     let config =
         to_string(&Config::default()).map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
-    write!(tempfile, "{}", config).map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
+    // the temporary file is deleted once dropped, so it's kept around until the end
+    let (path, _tempfile) = match config_path {
+        Some(path) if path.exists() => {
+            print_config(pid, &path)?;
+            (path, None)
+        }
+        Some(path) => {
+            std::fs::write(&path, &config)
+                .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
+            (path, None)
+        }
+        None => {
+            let mut tempfile = tempfile::Builder::new()
+                .suffix(".yaml")
+                .tempfile()
+                .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
+            write!(tempfile, "{}", config)
+                .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
 
-    // set correct permissions
-    let path = PathBuf::from(tempfile.path().clone());
-    {
-        let mut perms = std::fs::metadata(&path)
-            .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?
-            .permissions();
-        perms.set_mode(0o666);
-        std::fs::set_permissions(&path, perms)
-            .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
-    }
+            // set correct permissions
+            let path = PathBuf::from(tempfile.path().clone());
+            {
+                let mut perms = std::fs::metadata(&path)
+                    .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?
+                    .permissions();
+                perms.set_mode(0o666);
+                std::fs::set_permissions(&path, perms)
+                    .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
+            }
+            (path, Some(tempfile))
+        }
+    };
     println!(" :: edit [{:?}] file and see the live output", path);
 
     let (tx, rx) = channel();
@@ -47,34 +91,48 @@ pub fn run(pid: i32) -> BetrayalResult<()> {
         BetrayalError::ConfigFileError(format!("failed to spawn a file watcher :: {}", e))
     })?;
 
-    // Add a path to be watched. All files and directories at that path and
-    // below will be monitored for changes.
+    // editors saving atomically replace the file with a new one, which a watch on the file itself
+    // doesn't survive, so the directory is watched and its events are narrowed down to the file
+    let path = path
+        .canonicalize()
+        .map_err(|e| BetrayalError::ConfigFileError(e.to_string()))?;
+    let directory = path.parent().ok_or_else(|| {
+        BetrayalError::ConfigFileError(format!("{:?} has no parent directory", path))
+    })?;
     watcher
-        .watch(&path, RecursiveMode::NonRecursive)
+        .watch(directory, RecursiveMode::NonRecursive)
         .map_err(|e| {
             BetrayalError::ConfigFileError(format!("failed to spawn a file watcher :: {}", e))
         })?;
 
+    let mut last_render = Instant::now();
     loop {
-        match rx.recv() {
-            Ok(DebouncedEvent::Write(_)) => {
-                let config = read_to_string(&path).map_err(|e| {
-                    BetrayalError::ConfigFileError(format!("failed to read config file :: {}", e))
-                })?;
-                match from_str::<Config>(&config) {
-                    Ok(c) => {
-                        let result = c.result(pid);
-                        match result {
-                            Ok(result) => println!("{}", result.print(0)),
-                            Err(e) => {
-                                eprintln!("ERROR: \n {}", e.to_string())
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("bad format :: {}", e)
-                    }
+        // without a refresh interval this only wakes up for file events, other files in the
+        // directory changing mustn't push the next refresh back, so the wait is what's left of it
+        let event = match refresh {
+            Some(refresh) => rx.recv_timeout(
+                refresh
+                    .checked_sub(last_render.elapsed())
+                    .unwrap_or_default(),
+            ),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match event {
+            Ok(DebouncedEvent::Write(changed))
+            | Ok(DebouncedEvent::Create(changed))
+            | Ok(DebouncedEvent::Rename(_, changed))
+                if changed == path =>
+            {
+                if let Err(e) = print_config(pid, &path) {
+                    eprintln!("ERROR: \n {}", e);
+                }
+                last_render = Instant::now();
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Err(e) = print_config(pid, &path) {
+                    eprintln!("ERROR: \n {}", e);
                 }
+                last_render = Instant::now();
             }
             Err(e) => {
                 eprintln!("watch error: {:?}", e);
@@ -1,6 +1,9 @@
-use std::convert::{TryFrom, TryInto};
+use std::{
+    convert::{TryFrom, TryInto},
+    sync::Arc,
+};
 
-use rhai::{Engine, EvalAltResult, Scope};
+use rhai::{Engine, EvalAltResult, Scope, AST};
 
 use crate::error::{BetrayalError, BetrayalResult};
 
@@ -42,31 +45,136 @@ macro_rules! or_err {
     };
 }
 
-fn static_address(pid: i32, file: &str) -> Result<i64, Box<EvalAltResult>> {
-    let maps = or_err!(crate::ProcessQuery::<u8>::mappings_all(pid), format!("static_address :: {}", file));
-    let maps = maps.into_iter()
+/// non-writable mappings of `file`, sorted by base address
+fn static_bases(pid: i32, file: &str) -> Result<Vec<usize>, Box<EvalAltResult>> {
+    let maps = or_err!(
+        crate::ProcessQuery::<u8>::mappings_all(pid),
+        format!("static_address :: {}", file)
+    );
+    let mut bases = maps
+        .into_iter()
         .filter(|(_info, map)| match &map.pathname {
             procmaps::Path::MappedFile(s) => s == file && map.perms.writable == false,
             _ => false,
-        }).collect::<Vec<_>>();
-    if maps.len() > 1 {
-        return Err(format!("static_address :: more than one memory entry with name [{}] found ({})", file, maps.len()).into());
-    }
+        })
+        .map(|(_info, map)| map.base)
+        .collect::<Vec<_>>();
+    bases.sort_unstable();
+    Ok(bases)
+}
 
-    let (_info, map) = maps.into_iter()
-        .find(|(_info, map)| match &map.pathname {
-            procmaps::Path::MappedFile(s) => s == file && map.perms.writable == false,
+/// base of the lowest non-writable mapping of `file`
+fn static_address(pid: i32, file: &str) -> Result<i64, Box<EvalAltResult>> {
+    static_address_nth(pid, file, 0)
+}
+
+/// base of the `index`-th non-writable mapping of `file`, counting from the lowest address
+fn static_address_nth(pid: i32, file: &str, index: i64) -> Result<i64, Box<EvalAltResult>> {
+    let bases = static_bases(pid, file)?;
+    let count = bases.len();
+    let base = usize::try_from(index)
+        .ok()
+        .and_then(|index| bases.into_iter().nth(index))
+        .ok_or(format!(
+            "static_address() :: no section {} of {} : {} sections found",
+            index, file, count
+        ))?;
+    Ok(or_err!(
+        base.try_into(),
+        "that address doesn't fit in your address space"
+    ))
+}
+
+fn module_base(pid: i32, name: &str) -> Result<i64, Box<EvalAltResult>> {
+    let maps = or_err!(
+        crate::ProcessQuery::<u8>::mappings_all(pid),
+        format!("module_base :: {}", name)
+    );
+    let base = maps
+        .into_iter()
+        .filter(|(_info, map)| match &map.pathname {
+            procmaps::Path::MappedFile(s) => {
+                s == name || std::path::Path::new(s).file_name() == Some(std::ffi::OsStr::new(name))
+            }
             _ => false,
-        }).ok_or(format!("static_address() :: no such section : {}", file))?;
+        })
+        .map(|(_info, map)| map.base)
+        .min()
+        .ok_or(format!("module_base() :: no such module : {}", name))?;
+    Ok(or_err!(
+        base.try_into(),
+        "that address doesn't fit in your address space"
+    ))
+}
+
+fn read_pointer<T: crate::memory::ReadFromBytes + TryInto<i64>>(
+    pid: i32,
+    address: i64,
+) -> Result<i64, Box<EvalAltResult>> {
+    let address: usize = or_err!(address.try_into(), format!("bad address :: {}", address));
+    let (_info, value) = or_err!(
+        super::config_file::read_memory::<T>(pid, address),
+        format!("read at 0x{:x}", address)
+    );
+    Ok(or_err!(
+        value.try_into().map_err(|_| "value doesn't fit in i64"),
+        format!("read at 0x{:x}", address)
+    ))
+}
+
+fn read_u32(pid: i32, address: i64) -> Result<i64, Box<EvalAltResult>> {
+    read_pointer::<u32>(pid, address)
+}
 
-    // let (_info, map) = match maps
-    //     .first() {
-    //         Some(map) => map,
-    //         None => return Err(format!("static_address :: {} :: no such map", file).into())
-    //     };
-    Ok(or_err!(map.base.try_into(), "that address doesn't fit in your address space"))
+fn read_u64(pid: i32, address: i64) -> Result<i64, Box<EvalAltResult>> {
+    read_pointer::<u64>(pid, address)
 }
 
+/// a compiled `"f script <expr>"` expression, `value` and `address` are in scope and it
+/// has to evaluate to a bool. values are `i64` since the engine is built without floats
+#[derive(Clone)]
+pub struct ScriptFilter {
+    pub source: String,
+    compiled: Arc<(Engine, AST)>,
+}
+
+impl ScriptFilter {
+    pub fn new(source: &str) -> BetrayalResult<Self> {
+        let engine = Engine::new();
+        let ast = engine
+            .compile_expression(source)
+            .map_err(|e| BetrayalError::ScriptingError(e.to_string()))?;
+        Ok(Self {
+            source: source.to_string(),
+            compiled: Arc::new((engine, ast)),
+        })
+    }
+
+    /// errors (eg. the expression not returning a bool) count as not matching
+    pub fn matches(&self, address: usize, value: i64) -> bool {
+        let (engine, ast) = self.compiled.as_ref();
+        let mut scope = Scope::new();
+        scope.push("value", value);
+        scope.push("address", address as i64);
+        engine
+            .eval_ast_with_scope::<bool>(&mut scope, ast)
+            .unwrap_or(false)
+    }
+}
+
+impl std::fmt::Debug for ScriptFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ScriptFilter").field(&self.source).finish()
+    }
+}
+
+impl PartialEq for ScriptFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for ScriptFilter {}
 
 pub fn calculate_address(pid: i32, script: &str) -> BetrayalResult<usize> {
     let mut engine = Engine::new();
@@ -74,15 +182,37 @@ pub fn calculate_address(pid: i32, script: &str) -> BetrayalResult<usize> {
     // scope.push_constant(format!("SIZE_{}", "I32"), super::config_file::Field::I32.size());
     scope.push_constant("PID", pid);
     engine.register_result_fn("static_address", static_address);
+    engine.register_result_fn("static_address", static_address_nth);
+    engine.register_result_fn("module_base", module_base);
+    engine.register_result_fn("read_u32", read_u32);
+    engine.register_result_fn("read_u64", read_u64);
     engine.on_print(|x| println!(" :: :: :: {}", x));
     constant!(scope, I32);
     constant!(scope, I16);
     constant!(scope, U8);
+    constant!(scope, "BOOL", super::config_file::Field::Bool.size());
+    constant!(scope, I8);
     constant!(scope, F32);
     constant!(scope, F64);
+    constant!(scope, "POINTER", std::mem::size_of::<usize>());
 
     try_cast_to_usize::<i16>(&engine, scope.clone(), script)
         .or(try_cast_to_usize::<i32>(&engine, scope.clone(), script))
         .or(try_cast_to_usize::<i64>(&engine, scope.clone(), script))
         .or(try_cast_to_usize::<usize>(&engine, scope.clone(), script))
 }
+
+#[cfg(test)]
+mod test_scripting {
+    use super::*;
+
+    #[test]
+    fn test_script_filter() {
+        let filter = ScriptFilter::new("value > 100 && address % 8 == 0").unwrap();
+        assert!(filter.matches(0x1000, 101));
+        assert!(!filter.matches(0x1004, 101));
+        assert!(!filter.matches(0x1000, 100));
+        assert!(!ScriptFilter::new("value + 1").unwrap().matches(0, 1));
+        assert!(ScriptFilter::new("value >").is_err());
+    }
+}
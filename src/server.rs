@@ -0,0 +1,259 @@
+//! `--serve <socket>`, drives a session over a unix socket instead of the prompt
+//!
+//! every line sent to the socket is one JSON request and gets exactly one JSON line back, eg.
+//! `{"command": "filter", "filter": "e 2137"}` -> `{"response": "results", "results": [...]}`.
+//! values are sent as strings (in both directions) so that 64 bit integers survive JSON parsers
+//! that only know doubles.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancel,
+    commands::{self, Command},
+    error::{BetrayalError, BetrayalResult},
+    memory::{FromUserInput, ReadFromBytes},
+    pointer_map, AddressInfo, PointerMap, PointerMapLimits, ProcessQuery, StaticLocation,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// same syntax as the "f" command without the "f", eg. `"e 2137"` or `"r 15 300"`
+    Filter {
+        filter: String,
+    },
+    Results,
+    Write {
+        address: usize,
+        value: String,
+    },
+    Add {
+        address: usize,
+    },
+    Undo,
+    Clear,
+    /// builds a 64 bit pointer map and answers with its static chains, see `pointer_map`
+    PointerMap {
+        address: u64,
+        /// what used to be called depth
+        #[serde(alias = "depth")]
+        window: u64,
+        #[serde(default)]
+        levels: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResultEntry {
+    pub address: usize,
+    pub value: String,
+    pub writable: bool,
+    pub label: Option<String>,
+    pub static_location: Option<StaticLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "response", rename_all = "snake_case")]
+pub enum Response {
+    Results { results: Vec<ResultEntry> },
+    PointerChains { chains: Vec<String> },
+    Error { message: String },
+}
+
+fn server_error(what: &str, e: impl std::fmt::Display) -> BetrayalError {
+    BetrayalError::ServerError(format!("{} :: {}", what, e))
+}
+
+fn results<T: ReadFromBytes>(process: &ProcessQuery<T>) -> Response {
+    Response::Results {
+        results: process
+            .displayed_results()
+            .into_iter()
+            .map(|(info, address, value)| ResultEntry {
+                address: *address,
+                value: value.to_string(),
+                writable: info.writable,
+                label: process.labels.get(address).cloned(),
+                static_location: info.static_location(process.pid, *address),
+            })
+            .collect(),
+    }
+}
+
+fn handle<T: 'static + ReadFromBytes>(
+    session: &Mutex<ProcessQuery<T>>,
+    request: Request,
+) -> BetrayalResult<Response> {
+    let mut process = session.lock();
+    // there's no prompt to go back to, so a Ctrl-C only ever cancels the request it interrupted
+    cancel::reset();
+    match request {
+        Request::Filter { filter } => match format!("f {}", filter).parse::<Command<T>>()? {
+            Command::PerformFilter(filter) => {
+                process.perform_query(filter)?;
+                process.page = 0;
+            }
+            _ => {
+                return Err(BetrayalError::BadCommand(format!(
+                    "not a filter :: {}",
+                    filter
+                )))
+            }
+        },
+        Request::Results => process.update_results()?,
+        Request::Write { address, value } => {
+            let value = T::from_user_input(&value)
+                .ok_or_else(|| BetrayalError::BadCommand(format!("invalid value: {}", value)))?;
+            process.perform_write_literal(address, value)?;
+        }
+        Request::Add { address } => {
+            process.update_mappings()?;
+            let info = AddressInfo::from_address(&process, process.pid, address)?;
+            process.insert_results(info, address..address + 1);
+            process.update_results()?;
+        }
+        Request::Undo => {
+            process.undo()?;
+        }
+        Request::Clear => {
+            process.clear();
+        }
+        Request::PointerMap {
+            address,
+            window,
+            levels,
+        } => {
+            commands::check_pointer_window(window)?;
+            let levels = levels.unwrap_or(PointerMapLimits::default().max_levels);
+            // building the map takes a while, the other clients shouldn't wait for it
+            let pid = process.pid;
+            drop(process);
+            let map = PointerMap::U64(pointer_map::<u64>(pid, address, window, levels)?);
+            let chains = map.static_chains(pid)?;
+            session.lock().pointer_map = Some(map);
+            return Ok(Response::PointerChains {
+                chains: chains.iter().map(|chain| chain.to_string()).collect(),
+            });
+        }
+    }
+    Ok(results(&process))
+}
+
+fn respond<T: 'static + ReadFromBytes>(process: &Mutex<ProcessQuery<T>>, line: &str) -> String {
+    let response = serde_json::from_str::<Request>(line)
+        .map_err(|e| BetrayalError::BadCommand(format!("bad request :: {}", e)))
+        .and_then(|request| handle(process, request))
+        .unwrap_or_else(|e| Response::Error {
+            message: e.to_string(),
+        });
+    serde_json::to_string(&response).expect("responses always serialize")
+}
+
+fn serve_client<T: 'static + ReadFromBytes>(
+    process: &Mutex<ProcessQuery<T>>,
+    stream: UnixStream,
+) -> BetrayalResult<()> {
+    let mut writer = stream
+        .try_clone()
+        .map_err(|e| server_error("cloning a client stream", e))?;
+    for line in BufReader::new(stream).lines() {
+        let line = line.map_err(|e| server_error("reading a request", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", respond(process, &line))
+            .map_err(|e| server_error("writing a response", e))?;
+    }
+    Ok(())
+}
+
+/// listens on `socket` until the process is killed, each client gets its own thread
+/// but they all share (and lock) the same session
+pub fn serve<T: 'static + ReadFromBytes>(
+    process: Arc<Mutex<ProcessQuery<T>>>,
+    socket: &Path,
+) -> BetrayalResult<()> {
+    // a socket left behind by a previous run, anything else at that path is left alone
+    if let Ok(metadata) = std::fs::metadata(socket) {
+        if metadata.file_type().is_socket() {
+            std::fs::remove_file(socket).map_err(|e| server_error("removing a stale socket", e))?;
+        }
+    }
+    let listener = UnixListener::bind(socket).map_err(|e| server_error("binding", e))?;
+    println!(" :: listening on {:?}", socket);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!(" :: [WARN] :: failed to accept a client :: {}", e);
+                continue;
+            }
+        };
+        let process = Arc::clone(&process);
+        std::thread::spawn(move || {
+            if let Err(e) = serve_client(&process, stream) {
+                eprintln!(" :: [WARN] :: client disconnected :: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_server {
+    use super::*;
+
+    #[test]
+    fn test_request_schema() {
+        assert_eq!(
+            serde_json::from_str::<Request>(r#"{"command": "filter", "filter": "e 2137"}"#)
+                .unwrap(),
+            Request::Filter {
+                filter: "e 2137".to_string()
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(
+                r#"{"command": "pointer_map", "address": 4096, "window": 256, "levels": 3}"#
+            )
+            .unwrap(),
+            Request::PointerMap {
+                address: 4096,
+                window: 256,
+                levels: Some(3)
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(
+                r#"{"command": "pointer_map", "address": 4096, "depth": 256}"#
+            )
+            .unwrap(),
+            Request::PointerMap {
+                address: 4096,
+                window: 256,
+                levels: None
+            }
+        );
+        assert!(serde_json::from_str::<Request>(r#"{"command": "explode"}"#).is_err());
+    }
+
+    #[test]
+    fn test_errors_are_responses() {
+        let process = Mutex::new(ProcessQuery::<i32>::new(std::process::id() as i32));
+        let response = respond(&process, r#"{"command": "filter", "filter": "nonsense"}"#);
+        assert!(response.starts_with(r#"{"response":"error","message":"#));
+        let response = respond(&process, "not json");
+        assert!(response.starts_with(r#"{"response":"error","message":"#));
+    }
+}
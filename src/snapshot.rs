@@ -0,0 +1,113 @@
+//! comparing two "export csv" files, eg. one saved before and one after a long play session
+
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{
+    error::{BetrayalError, BetrayalResult},
+    memory::ReadFromBytes,
+};
+
+/// address -> value, as read back from a csv export
+pub type Snapshot<T> = BTreeMap<usize, T>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotDiff<T: ReadFromBytes> {
+    /// address, old value and new value, biggest changes first
+    pub changed: Vec<(usize, T, T)>,
+    /// only in the second snapshot
+    pub added: Vec<(usize, T)>,
+    /// only in the first snapshot
+    pub removed: Vec<(usize, T)>,
+}
+
+/// reads the address and value columns of `ProcessQuery::results_csv` output
+pub fn parse_snapshot<T: ReadFromBytes>(csv: &str) -> BetrayalResult<Snapshot<T>> {
+    csv.lines()
+        .skip(1) // header
+        .filter(|row| !row.trim().is_empty())
+        .enumerate()
+        .map(|(row, line)| {
+            let bad_row =
+                || BetrayalError::BadCommand(format!("bad csv row {} :: {}", row + 1, line));
+            // only the static location columns (after the value) can contain commas
+            let columns = line.splitn(6, ',').collect::<Vec<_>>();
+            match columns[..] {
+                [_index, address, _address_hex, value, ..] => Ok((
+                    address.parse::<usize>().map_err(|_| bad_row())?,
+                    T::from_user_input(value).ok_or_else(bad_row)?,
+                )),
+                _ => Err(bad_row()),
+            }
+        })
+        .collect()
+}
+
+pub fn load_snapshot<T: ReadFromBytes>(path: &Path) -> BetrayalResult<Snapshot<T>> {
+    let csv = std::fs::read_to_string(path)
+        .map_err(|e| BetrayalError::BadCommand(format!("failed to read {:?} :: {}", path, e)))?;
+    parse_snapshot(&csv)
+}
+
+pub fn diff_snapshots<T: ReadFromBytes>(
+    before: &Snapshot<T>,
+    after: &Snapshot<T>,
+) -> SnapshotDiff<T> {
+    let mut changed = before
+        .iter()
+        .filter_map(|(address, old)| match after.get(address) {
+            Some(new) if new != old => Some((*address, *old, *new)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    changed.sort_by(|(_, a_old, a_new), (_, b_old, b_new)| {
+        let magnitude = |old: &T, new: &T| (new.to_f64() - old.to_f64()).abs();
+        magnitude(b_old, b_new)
+            .partial_cmp(&magnitude(a_old, a_new))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    SnapshotDiff {
+        changed,
+        added: after
+            .iter()
+            .filter(|(address, _)| !before.contains_key(address))
+            .map(|(address, value)| (*address, *value))
+            .collect(),
+        removed: before
+            .iter()
+            .filter(|(address, _)| !after.contains_key(address))
+            .map(|(address, value)| (*address, *value))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod test_snapshot {
+    use super::*;
+
+    #[test]
+    fn test_parse_snapshot() {
+        let csv = "index,address_dec,address_hex,value,writable,static_location\n\
+                   0,4096,0x1000,15,true,\n\
+                   1,4100,0x1004,-3,false,\"static_address(PID,\"\"game\"\")+16\"\n";
+        let snapshot = parse_snapshot::<i32>(csv).unwrap();
+        assert_eq!(
+            snapshot.into_iter().collect::<Vec<_>>(),
+            vec![(4096, 15), (4100, -3)]
+        );
+        assert!(parse_snapshot::<i32>("header\n0,nope,0x0,1,true,\n").is_err());
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let before = vec![(0, 10), (4, 100), (8, 5), (12, 7)]
+            .into_iter()
+            .collect::<Snapshot<i32>>();
+        let after = vec![(0, 11), (4, 50), (8, 5), (16, 1)]
+            .into_iter()
+            .collect::<Snapshot<i32>>();
+        let diff = diff_snapshots(&before, &after);
+        assert_eq!(diff.changed, vec![(4, 100, 50), (0, 10, 11)]);
+        assert_eq!(diff.added, vec![(16, 1)]);
+        assert_eq!(diff.removed, vec![(12, 7)]);
+    }
+}
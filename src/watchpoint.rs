@@ -0,0 +1,206 @@
+//! "find out what writes to this address" using x86-64 hardware debug registers
+//!
+//! every thread of the target gets attached with `ptrace` and has DR0 / DR7 set up,
+//! so this needs the same permissions as a debugger: either run as root or allow it with
+//! `echo 0 | sudo tee /proc/sys/kernel/yama/ptrace_scope`.
+//! threads spawned while watching are not traced.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    mem::MaybeUninit,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use nix::{
+    errno::Errno,
+    libc,
+    sys::{
+        ptrace,
+        signal::Signal,
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
+
+use crate::error::{BetrayalError, BetrayalResult};
+
+const DR_CONTROL: usize = 7;
+const DR_STATUS: usize = 6;
+/// DR7 bits 16-17, break on data writes only
+const DR7_WRITE: u64 = 0b01 << 16;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// instruction pointer right after the writing instruction -> number of hits
+pub type WatchHits = BTreeMap<usize, usize>;
+
+fn ptrace_error(what: &str, e: nix::Error) -> BetrayalError {
+    BetrayalError::PtraceError(format!("{} :: {}", what, e))
+}
+
+fn debug_register_offset(index: usize) -> usize {
+    let user = MaybeUninit::<libc::user>::uninit();
+    let base = user.as_ptr() as usize;
+    let debug_registers = unsafe { std::ptr::addr_of!((*user.as_ptr()).u_debugreg) } as usize;
+    debug_registers - base + index * std::mem::size_of::<libc::c_ulonglong>()
+}
+
+fn poke_debug_register(tid: Pid, index: usize, value: u64) -> BetrayalResult<()> {
+    let result = unsafe {
+        libc::ptrace(
+            libc::PTRACE_POKEUSER,
+            tid.as_raw(),
+            debug_register_offset(index) as *mut libc::c_void,
+            value as *mut libc::c_void,
+        )
+    };
+    Errno::result(result)
+        .map(drop)
+        .map_err(|e| ptrace_error("setting a debug register", e))
+}
+
+fn peek_debug_register(tid: Pid, index: usize) -> BetrayalResult<u64> {
+    let result = unsafe {
+        Errno::clear();
+        libc::ptrace(
+            libc::PTRACE_PEEKUSER,
+            tid.as_raw(),
+            debug_register_offset(index) as *mut libc::c_void,
+            std::ptr::null_mut::<libc::c_void>(),
+        )
+    };
+    match Errno::result(result) {
+        Ok(value) | Err(nix::Error::Sys(Errno::UnknownErrno)) => Ok(value as u64),
+        Err(e) => Err(ptrace_error("reading a debug register", e)),
+    }
+}
+
+/// DR7 value enabling DR0 as a write watchpoint of `size` bytes
+fn control_register(size: usize) -> BetrayalResult<u64> {
+    let len = match size {
+        1 => 0b00,
+        2 => 0b01,
+        8 => 0b10,
+        4 => 0b11,
+        _ => {
+            return Err(BetrayalError::BadCommand(format!(
+                "watchpoints can only cover 1, 2, 4 or 8 bytes, not {}",
+                size
+            )))
+        }
+    };
+    Ok(1 | DR7_WRITE | len << 18)
+}
+
+fn thread_ids(pid: i32) -> BetrayalResult<Vec<Pid>> {
+    Ok(procfs::process::Process::new(pid)?
+        .tasks()?
+        .filter_map(|task| task.ok())
+        .map(|task| Pid::from_raw(task.tid))
+        .collect())
+}
+
+fn attach(tid: Pid, address: usize, control: u64) -> BetrayalResult<()> {
+    ptrace::attach(tid).map_err(|e| ptrace_error("attaching", e))?;
+    waitpid(tid, Some(WaitPidFlag::__WALL)).map_err(|e| ptrace_error("waiting for a thread", e))?;
+    let armed = poke_debug_register(tid, 0, address as u64)
+        .and_then(|_| poke_debug_register(tid, DR_CONTROL, control));
+    if armed.is_err() {
+        let _ = ptrace::detach(tid, None);
+    }
+    armed?;
+    ptrace::cont(tid, None).map_err(|e| ptrace_error("resuming a thread", e))
+}
+
+/// stops a running tracee, clears its watchpoint and lets it go
+fn release(pid: i32, tid: Pid) {
+    unsafe {
+        libc::syscall(libc::SYS_tgkill, pid, tid.as_raw(), libc::SIGSTOP);
+    }
+    loop {
+        match waitpid(tid, Some(WaitPidFlag::__WALL)) {
+            Ok(WaitStatus::Stopped(_, Signal::SIGSTOP)) => break,
+            Ok(WaitStatus::Stopped(_, signal)) => {
+                let signal = match signal {
+                    Signal::SIGTRAP => None,
+                    signal => Some(signal),
+                };
+                if ptrace::cont(tid, signal).is_err() {
+                    return;
+                }
+            }
+            _ => return,
+        }
+    }
+    let _ = poke_debug_register(tid, DR_CONTROL, 0);
+    let _ = ptrace::detach(tid, None);
+}
+
+/// traces every thread of `pid` until `stop` is set, calling `on_hit` for every write
+/// to `address`; the reported instruction pointer is the one right *after* the write
+pub fn watch_writes(
+    pid: i32,
+    address: usize,
+    size: usize,
+    stop: &AtomicBool,
+    mut on_hit: impl FnMut(Pid, usize),
+) -> BetrayalResult<WatchHits> {
+    if address % size != 0 {
+        return Err(BetrayalError::BadCommand(format!(
+            "0x{:x} is not aligned to {} bytes, hardware watchpoints require that",
+            address, size
+        )));
+    }
+    let control = control_register(size)?;
+    let mut traced = HashSet::new();
+    let mut hits = WatchHits::new();
+    let result = (|| -> BetrayalResult<()> {
+        for tid in thread_ids(pid)? {
+            attach(tid, address, control)?;
+            traced.insert(tid);
+        }
+        while !stop.load(Ordering::Relaxed) && !traced.is_empty() {
+            let status = waitpid(None, Some(WaitPidFlag::__WALL | WaitPidFlag::WNOHANG))
+                .map_err(|e| ptrace_error("waiting for a thread", e))?;
+            match status {
+                WaitStatus::StillAlive => std::thread::sleep(POLL_INTERVAL),
+                WaitStatus::Stopped(tid, Signal::SIGTRAP)
+                    if peek_debug_register(tid, DR_STATUS)? & 1 != 0 =>
+                {
+                    let instruction_pointer = ptrace::getregs(tid)
+                        .map_err(|e| ptrace_error("reading registers", e))?
+                        .rip as usize;
+                    *hits.entry(instruction_pointer).or_default() += 1;
+                    on_hit(tid, instruction_pointer);
+                    poke_debug_register(tid, DR_STATUS, 0)?;
+                    ptrace::cont(tid, None).map_err(|e| ptrace_error("resuming a thread", e))?;
+                }
+                WaitStatus::Stopped(tid, signal) => {
+                    ptrace::cont(tid, Some(signal))
+                        .map_err(|e| ptrace_error("resuming a thread", e))?;
+                }
+                WaitStatus::Exited(tid, _) | WaitStatus::Signaled(tid, _, _) => {
+                    traced.remove(&tid);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    })();
+    for tid in traced {
+        release(pid, tid);
+    }
+    result.map(|_| hits)
+}
+
+#[cfg(test)]
+mod test_watchpoint {
+    use super::*;
+
+    #[test]
+    fn test_control_register() {
+        assert_eq!(control_register(4).unwrap(), 0b1101 << 16 | 1);
+        assert_eq!(control_register(8).unwrap(), 0b1001 << 16 | 1);
+        assert!(control_register(3).is_err());
+    }
+}
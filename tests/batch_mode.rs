@@ -0,0 +1,37 @@
+use std::{
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[test]
+fn test_quit_ends_the_session() {
+    let script = std::env::temp_dir().join(format!("betrayal_batch_{}.txt", std::process::id()));
+    std::fs::write(&script, "q\n").unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_betrayal_engine"))
+        .arg("--pid")
+        .arg(std::process::id().to_string())
+        .arg("--script")
+        .arg(&script)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if started.elapsed() > TIMEOUT {
+            child.kill().unwrap();
+            panic!("betrayal_engine kept running after `q`");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_file(&script).unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches(" :: running in [").count(), 1);
+}